@@ -1,112 +1,387 @@
-use crate::data::{AuthResponse, ExchangeStation, Flight, ProductionLine, Ship, Site, StarSystem};
-use wasm_bindgen::JsCast;
+use crate::data::{
+    AuthResponse, ExchangePrice, ExchangeStation, Flight, Material, Planet, ProductionLine, Ship, Site, StarSystem, Warehouse,
+};
+use std::fmt;
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::task::Poll;
+use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{Request, RequestInit, RequestMode, Response, Headers};
 
-const FIO_API_BASE: &str = "https://rest.fnar.net";
+/// The public FIO REST API, used unless a mirror is configured
+pub const DEFAULT_API_BASE: &str = "https://rest.fnar.net";
 
-async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str, auth_token: Option<&str>) -> Result<T, String> {
+/// How long to wait for a response's headers, or for the next chunk of its body, before giving up
+pub const DEFAULT_TIMEOUT_MS: u32 = 15_000;
+
+// Delays before each retry of a failed GET request
+const RETRY_DELAYS_MS: [u32; 3] = [250, 500, 1000];
+
+// Wait after a 429 when the server doesn't say how long, and the longest wait we'll honor
+const RATE_LIMIT_DEFAULT_DELAY_SECS: u32 = 5;
+const RATE_LIMIT_MAX_DELAY_SECS: u32 = 30;
+
+/// Bytes of a response body received so far
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DownloadProgress {
+    pub received: u64,
+    pub total: Option<u64>, // From Content-Length, when the server sends it
+}
+
+/// Where API requests are sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiConfig {
+    pub base_url: String,
+    pub timeout_ms: u32,
+}
+
+impl ApiConfig {
+    /// Use the given base URL, falling back to the public API when it's blank
+    pub fn new(base_url: &str) -> Self {
+        let base_url = base_url.trim().trim_end_matches('/');
+        if base_url.is_empty() {
+            Self::default()
+        } else {
+            Self { base_url: base_url.to_string(), ..Self::default() }
+        }
+    }
+    
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_API_BASE.to_string(),
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+        }
+    }
+}
+
+/// Errors returned by the FIO API client
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    /// The request could not be sent or no response was received
+    Network(String),
+    /// The server responded with a non-success status code
+    Http(u16),
+    /// The response body could not be parsed
+    Parse(String),
+    /// The server rejected our credentials (HTTP 401/403)
+    Auth,
+    /// The request took too long
+    Timeout,
+    /// The response body stopped arriving after this many bytes. Not retried, since the
+    /// download would start over from the beginning.
+    Stalled(u64),
+    /// The server asked us to slow down (HTTP 429), optionally saying for how many seconds
+    RateLimited(Option<u32>),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Network(e) => write!(f, "Network error: {}", e),
+            ApiError::Http(status) => write!(f, "HTTP error: {}", status),
+            ApiError::Parse(e) => write!(f, "Parse error: {}", e),
+            ApiError::Auth => write!(f, "Not authorized"),
+            ApiError::Timeout => write!(f, "Request timed out"),
+            ApiError::Stalled(received) => write!(f, "Download stalled after {} KB", received / 1_000),
+            ApiError::RateLimited(_) => write!(f, "Rate limited, please try again later"),
+        }
+    }
+}
+
+impl ApiError {
+    fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ApiError::Auth,
+            429 => ApiError::RateLimited(None),
+            _ => ApiError::Http(status),
+        }
+    }
+    
+    // Errors that may go away if the request is simply repeated. Timeouts aren't retried so
+    // a hung server is reported after the configured timeout, not several times over.
+    fn is_transient(&self) -> bool {
+        match self {
+            ApiError::Network(_) => true,
+            ApiError::Http(status) => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+// Race a future against a timer. Whichever loses is dropped, which also cancels the timer.
+async fn with_timeout<F: Future>(future: F, timeout_ms: u32) -> Result<F::Output, ApiError> {
+    let mut future = pin!(future);
+    let mut timer = pin!(gloo_timers::future::TimeoutFuture::new(timeout_ms));
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        timer.as_mut().poll(cx).map(|()| Err(ApiError::Timeout))
+    }).await
+}
+
+// Send a request and return the parsed JSON body, giving up if the server goes quiet for
+// longer than the configured timeout
+async fn request_json(
+    config: &ApiConfig,
+    method: &str,
+    url: &str,
+    auth_token: Option<&str>,
+    body: Option<&str>,
+    on_progress: Option<&dyn Fn(DownloadProgress)>,
+) -> Result<JsValue, ApiError> {
     let opts = RequestInit::new();
-    opts.set_method("GET");
+    opts.set_method(method);
     opts.set_mode(RequestMode::Cors);
     
+    let headers = Headers::new().map_err(|e| ApiError::Network(format!("Failed to create headers: {:?}", e)))?;
     if let Some(token) = auth_token {
-        let headers = Headers::new().map_err(|e| format!("Failed to create headers: {:?}", e))?;
-        headers.set("Authorization", token).map_err(|e| format!("Failed to set auth header: {:?}", e))?;
-        opts.set_headers(&headers);
+        headers.set("Authorization", token)
+            .map_err(|e| ApiError::Network(format!("Failed to set auth header: {:?}", e)))?;
     }
+    if let Some(body) = body {
+        headers.set("Content-Type", "application/json")
+            .map_err(|e| ApiError::Network(format!("Failed to set content type: {:?}", e)))?;
+        opts.set_body(&JsValue::from_str(body));
+    }
+    opts.set_headers(&headers);
     
     let request = Request::new_with_str_and_init(url, &opts)
-        .map_err(|e| format!("Failed to create request: {:?}", e))?;
+        .map_err(|e| ApiError::Network(format!("Failed to create request: {:?}", e)))?;
+    
+    let window = web_sys::window().ok_or_else(|| ApiError::Network("No window object".to_string()))?;
     
-    let window = web_sys::window().ok_or("No window object")?;
-    let resp_value = JsFuture::from(window.fetch_with_request(&request))
-        .await
-        .map_err(|e| format!("Fetch error: {:?}", e))?;
+    // Only the headers are under a fixed deadline; the body is read with an idle timeout so
+    // large downloads can take as long as they need while data keeps arriving
+    let resp_value = with_timeout(JsFuture::from(window.fetch_with_request(&request)), config.timeout_ms)
+        .await?
+        .map_err(|e| ApiError::Network(format!("Fetch error: {:?}", e)))?;
     
     let resp: Response = resp_value
         .dyn_into()
-        .map_err(|_| "Response is not a Response object")?;
+        .map_err(|_| ApiError::Network("Response is not a Response object".to_string()))?;
     
     if !resp.ok() {
-        return Err(format!("HTTP error: {}", resp.status()));
+        return Err(match ApiError::from_status(resp.status()) {
+            ApiError::RateLimited(_) => ApiError::RateLimited(retry_after_secs(&resp)),
+            e => e,
+        });
     }
     
-    let json = JsFuture::from(resp.json().map_err(|e| format!("JSON error: {:?}", e))?)
-        .await
-        .map_err(|e| format!("JSON parse error: {:?}", e))?;
+    let Some(body) = resp.body() else {
+        return Err(ApiError::Parse("empty response body".to_string()));
+    };
+    read_json_body(&resp, body, config.timeout_ms, on_progress).await
+}
+
+// Read a response body chunk by chunk, then parse it as JSON. Fails if no chunk arrives
+// within `idle_timeout_ms`. Reports the bytes received after each chunk if asked to.
+async fn read_json_body(
+    resp: &Response,
+    body: web_sys::ReadableStream,
+    idle_timeout_ms: u32,
+    on_progress: Option<&dyn Fn(DownloadProgress)>,
+) -> Result<JsValue, ApiError> {
+    let total = resp.headers().get("Content-Length").ok()
+        .flatten()
+        .and_then(|length| length.trim().parse().ok());
+    let report = |received: u64| {
+        if let Some(on_progress) = on_progress {
+            on_progress(DownloadProgress { received, total });
+        }
+    };
+    let reader: web_sys::ReadableStreamDefaultReader = body.get_reader().unchecked_into();
     
-    serde_wasm_bindgen::from_value(json)
-        .map_err(|e| format!("Deserialization error: {}", e))
+    let mut bytes = Vec::new();
+    report(0);
+    loop {
+        let chunk = match with_timeout(JsFuture::from(reader.read()), idle_timeout_ms).await {
+            Ok(chunk) => chunk.map_err(|e| ApiError::Network(format!("Failed to read response: {:?}", e)))?,
+            Err(e) => {
+                // Release the connection instead of leaving the stream half-read
+                let _ = reader.cancel();
+                return Err(if bytes.is_empty() { e } else { ApiError::Stalled(bytes.len() as u64) });
+            }
+        };
+        let done = js_sys::Reflect::get(&chunk, &JsValue::from_str("done"))
+            .ok()
+            .and_then(|done| done.as_bool())
+            .unwrap_or(true);
+        if done {
+            break;
+        }
+        let value: js_sys::Uint8Array = js_sys::Reflect::get(&chunk, &JsValue::from_str("value"))
+            .and_then(|value| value.dyn_into())
+            .map_err(|e| ApiError::Network(format!("Unexpected response chunk: {:?}", e)))?;
+        bytes.extend(value.to_vec());
+        report(bytes.len() as u64);
+    }
+    
+    let text = String::from_utf8(bytes).map_err(|e| ApiError::Parse(e.to_string()))?;
+    js_sys::JSON::parse(&text).map_err(|e| ApiError::Parse(format!("{:?}", e)))
+}
+
+// Seconds from a Retry-After header. The HTTP-date form isn't supported and is treated as missing.
+fn retry_after_secs(resp: &Response) -> Option<u32> {
+    resp.headers().get("Retry-After").ok()??.trim().parse().ok()
 }
 
-pub async fn fetch_star_systems() -> Result<Vec<StarSystem>, String> {
-    let url = format!("{}/systemstars", FIO_API_BASE);
-    fetch_json(&url, None).await
+// GET a JSON body, retrying transient failures with exponential backoff and a rate-limited
+// request once after the server's requested delay
+async fn get_json_with_retry(
+    config: &ApiConfig,
+    url: &str,
+    auth_token: Option<&str>,
+    on_progress: Option<&dyn Fn(DownloadProgress)>,
+) -> Result<JsValue, ApiError> {
+    let mut delays = RETRY_DELAYS_MS.iter();
+    let mut rate_limit_retried = false;
+    loop {
+        match request_json(config, "GET", url, auth_token, None, on_progress).await {
+            Err(ApiError::RateLimited(retry_after)) if !rate_limit_retried => {
+                rate_limit_retried = true;
+                let delay_secs = retry_after
+                    .unwrap_or(RATE_LIMIT_DEFAULT_DELAY_SECS)
+                    .min(RATE_LIMIT_MAX_DELAY_SECS);
+                tracing::warn!("Rate limited on {}, retrying in {}s", url, delay_secs);
+                gloo_timers::future::TimeoutFuture::new(delay_secs * 1000).await;
+            }
+            Err(e) if e.is_transient() => match delays.next() {
+                Some(&delay) => {
+                    tracing::warn!("Retrying {} in {}ms after: {}", url, delay, e);
+                    gloo_timers::future::TimeoutFuture::new(delay).await;
+                }
+                None => return Err(e),
+            },
+            result => return result,
+        }
+    }
 }
 
-pub async fn fetch_exchange_stations() -> Result<Vec<ExchangeStation>, String> {
-    let url = format!("{}/exchange/station", FIO_API_BASE);
-    fetch_json(&url, None).await
+async fn fetch_json<T: serde::de::DeserializeOwned>(config: &ApiConfig, url: &str, auth_token: Option<&str>) -> Result<T, ApiError> {
+    let json = get_json_with_retry(config, url, auth_token, None).await?;
+    
+    serde_wasm_bindgen::from_value(json)
+        .map_err(|e| ApiError::Parse(e.to_string()))
 }
 
-pub async fn login(username: &str, password: &str) -> Result<AuthResponse, String> {
-    let url = format!("{}/auth/login", FIO_API_BASE);
+// Deserialize a JSON array element by element, skipping malformed records instead of
+// failing the whole response. Returns the good records and the number skipped.
+async fn fetch_json_array_lenient<T: serde::de::DeserializeOwned>(
+    config: &ApiConfig,
+    url: &str,
+    auth_token: Option<&str>,
+    on_progress: Option<&dyn Fn(DownloadProgress)>,
+) -> Result<(Vec<T>, usize), ApiError> {
+    let json = get_json_with_retry(config, url, auth_token, on_progress).await?;
     
-    let opts = RequestInit::new();
-    opts.set_method("POST");
-    opts.set_mode(RequestMode::Cors);
+    if !js_sys::Array::is_array(&json) {
+        return Err(ApiError::Parse("expected a JSON array".to_string()));
+    }
     
-    let headers = Headers::new().map_err(|e| format!("Failed to create headers: {:?}", e))?;
-    headers.set("Content-Type", "application/json").map_err(|e| format!("Failed to set content type: {:?}", e))?;
-    opts.set_headers(&headers);
+    let mut items = Vec::new();
+    let mut skipped = 0;
+    for (i, value) in js_sys::Array::from(&json).iter().enumerate() {
+        match serde_wasm_bindgen::from_value(value) {
+            Ok(item) => items.push(item),
+            Err(e) => {
+                tracing::warn!("Skipping malformed record {} from {}: {}", i, url, e);
+                skipped += 1;
+            }
+        }
+    }
+    
+    Ok((items, skipped))
+}
+
+/// Fetch all star systems, reporting download progress as the body arrives. Returns the
+/// systems that parsed plus a count of skipped records.
+pub async fn fetch_star_systems(config: &ApiConfig, on_progress: impl Fn(DownloadProgress)) -> Result<(Vec<StarSystem>, usize), ApiError> {
+    let url = config.url("/systemstars");
+    fetch_json_array_lenient(config, &url, None, Some(&on_progress)).await
+}
+
+pub async fn fetch_exchange_stations(config: &ApiConfig) -> Result<Vec<ExchangeStation>, ApiError> {
+    let url = config.url("/exchange/station");
+    fetch_json(config, &url, None).await
+}
+
+/// Fetch prices for every material on every exchange, skipping malformed records
+pub async fn fetch_exchange_prices(config: &ApiConfig) -> Result<Vec<ExchangePrice>, ApiError> {
+    let url = config.url("/exchange/full");
+    let (prices, skipped) = fetch_json_array_lenient(config, &url, None, None).await?;
+    if skipped > 0 {
+        tracing::warn!("Skipped {} malformed exchange prices", skipped);
+    }
+    Ok(prices)
+}
+
+pub async fn fetch_planets_full(config: &ApiConfig) -> Result<Vec<Planet>, ApiError> {
+    let url = config.url("/planet/allplanets/full");
+    fetch_json(config, &url, None).await
+}
+
+pub async fn fetch_system_planets(config: &ApiConfig, system_id: &str) -> Result<Vec<Planet>, ApiError> {
+    let url = config.url(&format!("/planet/allplanets/{}", system_id));
+    fetch_json(config, &url, None).await
+}
+
+pub async fn fetch_materials(config: &ApiConfig) -> Result<Vec<Material>, ApiError> {
+    let url = config.url("/material/allmaterials");
+    fetch_json(config, &url, None).await
+}
+
+pub async fn login(config: &ApiConfig, username: &str, password: &str) -> Result<AuthResponse, ApiError> {
+    let url = config.url("/auth/login");
     
     let body = serde_json::json!({
         "UserName": username,
         "Password": password
     });
-    opts.set_body(&wasm_bindgen::JsValue::from_str(&body.to_string()));
-    
-    let request = Request::new_with_str_and_init(&url, &opts)
-        .map_err(|e| format!("Failed to create request: {:?}", e))?;
-    
-    let window = web_sys::window().ok_or("No window object")?;
-    let resp_value = JsFuture::from(window.fetch_with_request(&request))
-        .await
-        .map_err(|e| format!("Fetch error: {:?}", e))?;
     
-    let resp: Response = resp_value
-        .dyn_into()
-        .map_err(|_| "Response is not a Response object")?;
+    let json = request_json(config, "POST", &url, None, Some(&body.to_string()), None).await?;
     
-    if !resp.ok() {
-        return Err(format!("Login failed: HTTP {}", resp.status()));
-    }
+    serde_wasm_bindgen::from_value(json)
+        .map_err(|e| ApiError::Parse(e.to_string()))
+}
+
+pub async fn refresh_auth_token(config: &ApiConfig, auth_token: &str) -> Result<AuthResponse, ApiError> {
+    let url = config.url("/auth/refreshauthtoken");
     
-    let json = JsFuture::from(resp.json().map_err(|e| format!("JSON error: {:?}", e))?)
-        .await
-        .map_err(|e| format!("JSON parse error: {:?}", e))?;
+    let json = request_json(config, "POST", &url, Some(auth_token), None, None).await?;
     
     serde_wasm_bindgen::from_value(json)
-        .map_err(|e| format!("Deserialization error: {}", e))
+        .map_err(|e| ApiError::Parse(e.to_string()))
+}
+
+pub async fn fetch_ships(config: &ApiConfig, username: &str, auth_token: &str) -> Result<Vec<Ship>, ApiError> {
+    let url = config.url(&format!("/ship/ships/{}", username));
+    fetch_json(config, &url, Some(auth_token)).await
 }
 
-pub async fn fetch_ships(username: &str, auth_token: &str) -> Result<Vec<Ship>, String> {
-    let url = format!("{}/ship/ships/{}", FIO_API_BASE, username);
-    fetch_json(&url, Some(auth_token)).await
+pub async fn fetch_sites(config: &ApiConfig, username: &str, auth_token: &str) -> Result<Vec<Site>, ApiError> {
+    let url = config.url(&format!("/sites/{}", username));
+    fetch_json(config, &url, Some(auth_token)).await
 }
 
-pub async fn fetch_sites(username: &str, auth_token: &str) -> Result<Vec<Site>, String> {
-    let url = format!("{}/sites/{}", FIO_API_BASE, username);
-    fetch_json(&url, Some(auth_token)).await
+pub async fn fetch_warehouses(config: &ApiConfig, username: &str, auth_token: &str) -> Result<Vec<Warehouse>, ApiError> {
+    let url = config.url(&format!("/sites/warehouses/{}", username));
+    fetch_json(config, &url, Some(auth_token)).await
 }
 
-pub async fn fetch_flights(username: &str, auth_token: &str) -> Result<Vec<Flight>, String> {
-    let url = format!("{}/ship/flights/{}", FIO_API_BASE, username);
-    fetch_json(&url, Some(auth_token)).await
+pub async fn fetch_flights(config: &ApiConfig, username: &str, auth_token: &str) -> Result<Vec<Flight>, ApiError> {
+    let url = config.url(&format!("/ship/flights/{}", username));
+    fetch_json(config, &url, Some(auth_token)).await
 }
 
-pub async fn fetch_production(username: &str, auth_token: &str) -> Result<Vec<ProductionLine>, String> {
-    let url = format!("{}/production/{}", FIO_API_BASE, username);
-    fetch_json(&url, Some(auth_token)).await
+pub async fn fetch_production(config: &ApiConfig, username: &str, auth_token: &str) -> Result<Vec<ProductionLine>, ApiError> {
+    let url = config.url(&format!("/production/{}", username));
+    fetch_json(config, &url, Some(auth_token)).await
 }
@@ -1,6 +1,7 @@
-use petgraph::graph::{NodeIndex, UnGraph};
+use petgraph::graph::{DiGraph, NodeIndex, UnGraph};
+use petgraph::unionfind::UnionFind;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemConnection {
@@ -59,6 +60,21 @@ pub struct ExchangeStation {
     pub comex_name: String,
 }
 
+// One material's order book summary on one exchange, from /exchange/full
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangePrice {
+    #[serde(rename = "MaterialTicker")]
+    pub material_ticker: String,
+    #[serde(rename = "ExchangeCode")]
+    pub exchange_code: String,
+    #[serde(rename = "Ask")]
+    pub ask: Option<f64>,
+    #[serde(rename = "Bid")]
+    pub bid: Option<f64>,
+    #[serde(rename = "Currency")]
+    pub currency: Option<String>,
+}
+
 // Ship data from /ship/ships/{username}
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ship {
@@ -112,6 +128,29 @@ pub struct Ship {
     pub timestamp: Option<String>,
 }
 
+/// World units of estimated FTL range per unit of emitter power per tonne of ship mass.
+/// A rough calibration, not taken from the game's fuel formula.
+pub const FTL_RANGE_PER_POWER_TO_MASS: f64 = 40.0;
+
+impl Ship {
+    /// Whether the ship has an FTL fuel store and so can jump between systems
+    pub fn is_ftl_capable(&self) -> bool {
+        self.ftl_fuel_store_id.as_deref().is_some_and(|id| !id.is_empty())
+    }
+
+    /// Approximate FTL range in world units: `emitter_power / mass * FTL_RANGE_PER_POWER_TO_MASS`.
+    /// A stronger emitter carries a lighter ship further per jump. Fuel on board isn't known,
+    /// so this is an indication rather than a limit. `None` for ships without FTL or physics data.
+    pub fn ftl_range_estimate(&self) -> Option<f32> {
+        if !self.is_ftl_capable() {
+            return None;
+        }
+        let emitter_power = self.emitter_power.filter(|&power| power > 0.0)?;
+        let mass = self.mass.filter(|&mass| mass > 0.0)?;
+        Some((emitter_power / mass * FTL_RANGE_PER_POWER_TO_MASS) as f32)
+    }
+}
+
 // Site data from /sites/{username}
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Site {
@@ -135,6 +174,22 @@ pub struct Site {
     pub timestamp: Option<String>,
 }
 
+// Warehouse (storage) data from /sites/warehouses/{username}
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Warehouse {
+    #[serde(rename = "WarehouseId", default)]
+    pub warehouse_id: Option<String>,
+    #[serde(rename = "StoreId", default)]
+    pub store_id: Option<String>,
+    #[serde(rename = "Units", default)]
+    pub units: Option<i32>,
+    #[serde(rename = "LocationName", default)]
+    pub location_name: Option<String>,
+    // Planet natural id or CX station natural id
+    #[serde(rename = "LocationNaturalId", default)]
+    pub location_natural_id: Option<String>,
+}
+
 // Auth response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthResponse {
@@ -197,6 +252,51 @@ pub struct ProductionLine {
     pub orders: Option<Vec<ProductionOrder>>,
 }
 
+// Resource deposit on a planet
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PlanetResource {
+    #[serde(rename = "MaterialId", default)]
+    pub material_id: Option<String>,
+    #[serde(rename = "ResourceType", default)]
+    pub resource_type: Option<String>,
+    #[serde(rename = "Factor", default)]
+    pub factor: Option<f64>,
+}
+
+// Planet data from /planet/allplanets/full
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Planet {
+    #[serde(rename = "PlanetId", default)]
+    pub planet_id: Option<String>,
+    #[serde(rename = "PlanetNaturalId", default)]
+    pub planet_natural_id: Option<String>,
+    #[serde(rename = "PlanetName", default)]
+    pub planet_name: Option<String>,
+    #[serde(rename = "Resources", default)]
+    pub resources: Option<Vec<PlanetResource>>,
+}
+
+// Material data from /material/allmaterials
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Material {
+    #[serde(rename = "MaterialId", default)]
+    pub material_id: Option<String>,
+    #[serde(rename = "Ticker", default)]
+    pub ticker: Option<String>,
+    #[serde(rename = "Name", default)]
+    pub name: Option<String>,
+}
+
+// A resource deposit on one of a system's planets
+#[derive(Debug, Clone)]
+pub struct ResourceDeposit {
+    pub planet_name: String,
+    pub factor: f64,
+}
+
+// Union of all planets' resources in a system, keyed by material ticker
+pub type SystemResources = BTreeMap<String, Vec<ResourceDeposit>>;
+
 // Calculated daily rate for a material
 #[derive(Debug, Clone)]
 pub struct MaterialRate {
@@ -253,6 +353,30 @@ pub struct FlightSegment {
     pub ftl_fuel_consumption: Option<f64>,
 }
 
+fn system_line_natural_id(lines: Option<&Vec<FlightLine>>) -> Option<String> {
+    lines?.iter()
+        .find(|line| line.line_type.as_deref() == Some("system"))
+        .and_then(|line| line.line_natural_id.clone())
+}
+
+impl FlightSegment {
+    pub fn origin_system_natural_id(&self) -> Option<String> {
+        system_line_natural_id(self.origin_lines.as_ref())
+    }
+    
+    pub fn destination_system_natural_id(&self) -> Option<String> {
+        system_line_natural_id(self.destination_lines.as_ref())
+    }
+    
+    pub fn is_jump(&self) -> bool {
+        self.segment_type.as_deref().is_some_and(|t| t.eq_ignore_ascii_case("JUMP"))
+    }
+    
+    pub fn duration_ms(&self) -> Option<f64> {
+        Some((self.arrival_time_epoch_ms? - self.departure_time_epoch_ms?) as f64)
+    }
+}
+
 // Flight data from /ship/flights/{username}
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Flight {
@@ -287,20 +411,59 @@ pub struct Flight {
 impl Flight {
     /// Extract the origin system natural ID from the first segment's origin lines
     pub fn origin_system_natural_id(&self) -> Option<String> {
-        self.segments.as_ref()?.first()?
-            .origin_lines.as_ref()?
-            .iter()
-            .find(|line| line.line_type.as_deref() == Some("system"))
-            .and_then(|line| line.line_natural_id.clone())
+        self.segments.as_ref()?.first()?.origin_system_natural_id()
     }
     
     /// Extract the destination system natural ID from the last segment's destination lines
     pub fn destination_system_natural_id(&self) -> Option<String> {
-        self.segments.as_ref()?.last()?
-            .destination_lines.as_ref()?
-            .iter()
-            .find(|line| line.line_type.as_deref() == Some("system"))
-            .and_then(|line| line.line_natural_id.clone())
+        self.segments.as_ref()?.last()?.destination_system_natural_id()
+    }
+    
+    /// Whether any segment of this flight starts or ends in the given system
+    pub fn passes_through(&self, system_id: &str) -> bool {
+        self.segments.iter().flatten().any(|segment| {
+            segment.origin_system_natural_id().as_deref() == Some(system_id)
+                || segment.destination_system_natural_id().as_deref() == Some(system_id)
+        })
+    }
+}
+
+/// Average leg durations observed in the player's own flights, used to estimate route travel time
+#[derive(Debug, Clone, Copy)]
+pub struct TravelTimeEstimate {
+    pub jump_ms: f64,
+    pub in_system_leg_ms: f64, // Everything before the first or after the last jump of a flight
+}
+
+impl TravelTimeEstimate {
+    pub fn from_flights(flights: &[Flight]) -> Option<Self> {
+        let mut jumps = Vec::new();
+        let mut in_system_legs = Vec::new();
+        for segments in flights.iter().filter_map(|f| f.segments.as_ref()) {
+            let (Some(first_jump), Some(last_jump)) = (
+                segments.iter().position(FlightSegment::is_jump),
+                segments.iter().rposition(FlightSegment::is_jump),
+            ) else {
+                continue;
+            };
+            jumps.extend(segments.iter().filter(|s| s.is_jump()).filter_map(FlightSegment::duration_ms));
+            for leg in [&segments[..first_jump], &segments[last_jump + 1..]] {
+                if !leg.is_empty() {
+                    in_system_legs.push(leg.iter().filter_map(FlightSegment::duration_ms).sum::<f64>());
+                }
+            }
+        }
+        
+        let mean = |values: &[f64]| (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64);
+        Some(TravelTimeEstimate {
+            jump_ms: mean(&jumps)?,
+            in_system_leg_ms: mean(&in_system_legs).unwrap_or(0.0),
+        })
+    }
+    
+    /// Estimated time for a trip with the given number of jumps, including departure and arrival legs
+    pub fn route_ms(&self, jumps: usize) -> f64 {
+        jumps as f64 * self.jump_ms + 2.0 * self.in_system_leg_ms
     }
 }
 
@@ -309,20 +472,45 @@ impl Flight {
 pub struct FlightPath {
     pub origin_system_id: String,
     pub destination_system_id: String,
-    #[allow(dead_code)]
+    pub ship_id: Option<String>,
     pub ship_registration: Option<String>,
+    pub departure_time_epoch_ms: Option<i64>,
+    pub arrival_time_epoch_ms: Option<i64>,
     pub is_in_system: bool, // true if origin == destination (in-system flight)
 }
 
+impl FlightPath {
+    /// Fraction of the flight completed at the given time, clamped to 0..=1
+    pub fn progress(&self, now_ms: f64) -> Option<f32> {
+        let departure = self.departure_time_epoch_ms? as f64;
+        let arrival = self.arrival_time_epoch_ms? as f64;
+        if arrival <= departure {
+            return None;
+        }
+        Some(((now_ms - departure) / (arrival - departure)).clamp(0.0, 1.0) as f32)
+    }
+
+    /// Time left until arrival at the given time, negative once the arrival time has passed
+    pub fn remaining_ms(&self, now_ms: f64) -> Option<f64> {
+        Some(self.arrival_time_epoch_ms? as f64 - now_ms)
+    }
+}
+
 // User data aggregated from various endpoints
 #[derive(Debug, Clone, Default)]
 pub struct UserData {
     #[allow(dead_code)]
     pub username: String,
+    pub ships: Vec<Ship>,
+    pub sites: Vec<Site>, // Sorted by planet name
     pub ship_system_ids: HashSet<String>,
+    pub ftl_ship_system_ids: HashSet<String>, // Systems with at least one docked FTL-capable ship
     pub base_system_ids: HashSet<String>,
+    pub warehouse_locations: HashSet<String>, // planet or station natural ids
+    pub flights: Vec<Flight>, // Raw flights, kept for their segment details
     pub flight_paths: Vec<FlightPath>,
     pub base_production: Vec<BaseProduction>, // Production rates per base
+    pub load_errors: Vec<(&'static str, String)>, // Endpoints that failed to load, with the error
 }
 
 // System markers for visualization
@@ -330,15 +518,46 @@ pub struct UserData {
 pub enum SystemMarker {
     CommodityExchange,
     Base,
+    Warehouse,
     Ship,
 }
 
 impl SystemMarker {
-    pub fn color(&self) -> egui::Color32 {
+    /// Marker color. The color-blind palette is taken from Okabe-Ito and avoids red/green pairs.
+    pub fn color(&self, color_blind: bool) -> egui::Color32 {
+        match (self, color_blind) {
+            (SystemMarker::CommodityExchange, false) => egui::Color32::from_rgb(255, 100, 100), // Red
+            (SystemMarker::Base, false) => egui::Color32::from_rgb(100, 255, 100), // Green
+            (SystemMarker::Warehouse, false) => egui::Color32::from_rgb(255, 170, 60), // Orange
+            (SystemMarker::Ship, false) => egui::Color32::from_rgb(100, 150, 255), // Blue
+            (SystemMarker::CommodityExchange, true) => egui::Color32::from_rgb(230, 159, 0), // Orange
+            (SystemMarker::Base, true) => egui::Color32::from_rgb(86, 180, 233), // Sky blue
+            (SystemMarker::Warehouse, true) => egui::Color32::from_rgb(204, 121, 167), // Reddish purple
+            (SystemMarker::Ship, true) => egui::Color32::from_rgb(240, 228, 66), // Yellow
+        }
+    }
+
+    /// Colored dot matching `color`, for labels
+    pub fn emoji(&self, color_blind: bool) -> &'static str {
+        match (self, color_blind) {
+            (SystemMarker::CommodityExchange, false) => "🔴",
+            (SystemMarker::Base, false) => "🟢",
+            (SystemMarker::Warehouse, false) => "🟠",
+            (SystemMarker::Ship, false) => "🔵",
+            (SystemMarker::CommodityExchange, true) => "🟠",
+            (SystemMarker::Base, true) => "🔵",
+            (SystemMarker::Warehouse, true) => "🟣",
+            (SystemMarker::Ship, true) => "🟡",
+        }
+    }
+
+    /// Number of dashes in this marker's ring in color-blind mode, or 0 for a solid ring
+    pub fn ring_dashes(&self) -> usize {
         match self {
-            SystemMarker::CommodityExchange => egui::Color32::from_rgb(255, 100, 100), // Red
-            SystemMarker::Base => egui::Color32::from_rgb(100, 255, 100), // Green
-            SystemMarker::Ship => egui::Color32::from_rgb(100, 150, 255), // Blue
+            SystemMarker::CommodityExchange => 0,
+            SystemMarker::Base => 12,
+            SystemMarker::Warehouse => 6,
+            SystemMarker::Ship => 3,
         }
     }
 }
@@ -352,7 +571,7 @@ pub struct StarNode {
     pub sector_id: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum StarType {
     O, // Blue
     B, // Blue-white
@@ -365,6 +584,17 @@ pub enum StarType {
 }
 
 impl StarType {
+    pub const ALL: [StarType; 8] = [
+        StarType::O,
+        StarType::B,
+        StarType::A,
+        StarType::F,
+        StarType::G,
+        StarType::K,
+        StarType::M,
+        StarType::Unknown,
+    ];
+
     pub fn from_str(s: &str) -> Self {
         match s.chars().next() {
             Some('O') => StarType::O,
@@ -378,6 +608,34 @@ impl StarType {
         }
     }
 
+    /// Short description of the spectral class's color
+    pub fn description(&self) -> &'static str {
+        match self {
+            StarType::O => "Blue",
+            StarType::B => "Blue-white",
+            StarType::A => "White",
+            StarType::F => "Yellow-white",
+            StarType::G => "Yellow",
+            StarType::K => "Orange",
+            StarType::M => "Red",
+            StarType::Unknown => "Unknown",
+        }
+    }
+
+    /// Relative drawing size: hotter classes are drawn slightly larger, cooler ones smaller.
+    /// Kept within ±20% so small stars stay easy to click.
+    pub fn size_scale(&self) -> f32 {
+        match self {
+            StarType::O => 1.2,
+            StarType::B => 1.15,
+            StarType::A => 1.1,
+            StarType::F => 1.05,
+            StarType::G | StarType::Unknown => 1.0,
+            StarType::K => 0.9,
+            StarType::M => 0.8,
+        }
+    }
+
     pub fn color(&self) -> egui::Color32 {
         match self {
             StarType::O => egui::Color32::from_rgb(155, 176, 255),
@@ -392,6 +650,16 @@ impl StarType {
     }
 }
 
+impl StarNode {
+    /// Straight-line distance to another star in world units
+    pub fn distance_to(&self, other: &StarNode) -> f32 {
+        let dx = self.position[0] - other.position[0];
+        let dy = self.position[1] - other.position[1];
+        let dz = self.position[2] - other.position[2];
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+}
+
 impl From<&StarSystem> for StarNode {
     fn from(sys: &StarSystem) -> Self {
         StarNode {
@@ -404,11 +672,158 @@ impl From<&StarSystem> for StarNode {
     }
 }
 
+// Axis-aligned bounding box of all star positions
+#[derive(Debug, Clone, Copy)]
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Bounds {
+    /// Smallest box containing all positions, or a zero-sized box at the origin if there are none
+    pub fn from_positions(positions: impl IntoIterator<Item = [f32; 3]>) -> Self {
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        let mut any = false;
+        for position in positions {
+            any = true;
+            for (axis, &value) in position.iter().enumerate() {
+                min[axis] = min[axis].min(value);
+                max[axis] = max[axis].max(value);
+            }
+        }
+        if any {
+            Bounds { min, max }
+        } else {
+            Bounds { min: [0.0; 3], max: [0.0; 3] }
+        }
+    }
+
+    /// Length of the box's diagonal
+    pub fn diagonal(&self) -> f32 {
+        self.size().iter().map(|d| d * d).sum::<f32>().sqrt()
+    }
+
+    pub fn size(&self) -> [f32; 3] {
+        [
+            self.max[0] - self.min[0],
+            self.max[1] - self.min[1],
+            self.max[2] - self.min[2],
+        ]
+    }
+}
+
+// Graph-derived statistics, computed once when the map is built
+#[derive(Debug, Clone)]
+pub struct GalaxyStats {
+    pub components: Vec<Vec<NodeIndex>>, // Largest first
+    pub bounds: Bounds,
+    // Systems at the origin, with non-finite coordinates, or sharing a position with another system
+    pub suspicious_nodes: Vec<NodeIndex>,
+    pub star_type_counts: Vec<(StarType, usize)>, // In StarType::ALL order
+    pub max_star_type_count: usize, // Count of the most common star type, for scaling histograms
+    pub average_degree: f32,
+    pub max_degree: Option<(NodeIndex, usize)>, // Best-connected system and its number of connections
+    pub one_way_edges: usize, // Connections listed by only one of their two systems
+}
+
+impl GalaxyStats {
+    fn compute(graph: &UnGraph<StarNode, ()>, directed: &DiGraph<(), ()>, degrees: &[usize]) -> Self {
+        let max_degree = degrees.iter()
+            .enumerate()
+            .map(|(i, &degree)| (NodeIndex::new(i), degree))
+            .max_by_key(|&(_, degree)| degree);
+        let average_degree = if degrees.is_empty() {
+            0.0
+        } else {
+            degrees.iter().sum::<usize>() as f32 / degrees.len() as f32
+        };
+        let star_type_counts: Vec<(StarType, usize)> = StarType::ALL
+            .iter()
+            .map(|&star_type| (star_type, graph.node_weights().filter(|n| n.star_type == star_type).count()))
+            .collect();
+
+        GalaxyStats {
+            components: Self::find_components(graph),
+            average_degree,
+            max_degree,
+            one_way_edges: directed.edge_indices()
+                .filter_map(|edge| directed.edge_endpoints(edge))
+                .filter(|&(a, b)| !directed.contains_edge(b, a))
+                .count(),
+            bounds: Bounds::from_positions(graph.node_weights().map(|node| node.position)),
+            suspicious_nodes: Self::find_suspicious_nodes(graph),
+            max_star_type_count: star_type_counts.iter().map(|&(_, count)| count).max().unwrap_or(0),
+            star_type_counts,
+        }
+    }
+
+    fn find_components(graph: &UnGraph<StarNode, ()>) -> Vec<Vec<NodeIndex>> {
+        let mut sets = UnionFind::new(graph.node_count());
+        for edge in graph.edge_indices() {
+            if let Some((a, b)) = graph.edge_endpoints(edge) {
+                sets.union(a.index(), b.index());
+            }
+        }
+
+        let mut components: HashMap<usize, Vec<NodeIndex>> = HashMap::new();
+        for idx in graph.node_indices() {
+            components.entry(sets.find(idx.index())).or_default().push(idx);
+        }
+        let mut components: Vec<Vec<NodeIndex>> = components.into_values().collect();
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+        components
+    }
+
+    fn find_suspicious_nodes(graph: &UnGraph<StarNode, ()>) -> Vec<NodeIndex> {
+        let mut by_position: HashMap<[u32; 3], Vec<NodeIndex>> = HashMap::new();
+        for idx in graph.node_indices() {
+            let position = graph[idx].position;
+            by_position.entry(position.map(f32::to_bits)).or_default().push(idx);
+        }
+
+        let mut suspicious: Vec<NodeIndex> = graph.node_indices()
+            .filter(|&idx| {
+                let position = graph[idx].position;
+                position == [0.0; 3]
+                    || position.iter().any(|v| !v.is_finite())
+                    || by_position[&position.map(f32::to_bits)].len() > 1
+            })
+            .collect();
+        suspicious.sort();
+        suspicious
+    }
+}
+
+/// How important a system is as a junction in the jump network
+#[derive(Debug, Clone, Copy)]
+pub struct HubScore {
+    pub node: NodeIndex,
+    pub degree: usize,
+    /// Estimated number of shortest paths between other systems that pass through this one
+    pub betweenness: f32,
+}
+
 pub struct StarMap {
     pub graph: UnGraph<StarNode, ()>,
+    // Connections as listed by each system, keeping their direction. Node indices match `graph`.
+    pub directed: DiGraph<(), ()>,
     #[allow(dead_code)]
     id_to_index: HashMap<String, NodeIndex>,
     pub natural_id_to_node: HashMap<String, NodeIndex>,
+    pub stats: GalaxyStats,
+    // Number of connections per system, indexed by node index
+    degrees: Vec<usize>,
+    pub search_index: Vec<SearchKeys>,
+}
+
+/// Lowercased text a system can be searched by, built once with the map
+#[derive(Debug, Clone)]
+pub struct SearchKeys {
+    pub node: NodeIndex,
+    pub name: String,
+    pub natural_id: String,
+    pub sector_id: String,
 }
 
 
@@ -416,6 +831,7 @@ pub struct StarMap {
 impl StarMap {
     pub fn from_systems(systems: Vec<StarSystem>) -> Self {
         let mut graph = UnGraph::new_undirected();
+        let mut directed = DiGraph::new();
         let mut id_to_index = HashMap::new();
         let mut natural_id_to_node = HashMap::new();
 
@@ -423,6 +839,7 @@ impl StarMap {
         for sys in &systems {
             let node = StarNode::from(sys);
             let idx = graph.add_node(node);
+            directed.add_node(());
             id_to_index.insert(sys.system_id.clone(), idx);
             natural_id_to_node.insert(sys.natural_id.clone(), idx);
         }
@@ -432,6 +849,9 @@ impl StarMap {
             if let Some(&from_idx) = id_to_index.get(&sys.system_id) {
                 for conn in &sys.connections {
                     if let Some(&to_idx) = id_to_index.get(&conn.connecting_id) {
+                        if !directed.contains_edge(from_idx, to_idx) {
+                            directed.add_edge(from_idx, to_idx, ());
+                        }
                         // Only add edge if it doesn't exist (undirected graph)
                         if !graph.contains_edge(from_idx, to_idx) {
                             graph.add_edge(from_idx, to_idx, ());
@@ -441,11 +861,222 @@ impl StarMap {
             }
         }
 
+        let degrees: Vec<usize> = graph.node_indices().map(|idx| graph.neighbors(idx).count()).collect();
+        let stats = GalaxyStats::compute(&graph, &directed, &degrees);
+        let search_index = graph.node_indices()
+            .map(|idx| {
+                let node = &graph[idx];
+                SearchKeys {
+                    node: idx,
+                    name: node.name.to_lowercase(),
+                    natural_id: node.natural_id.to_lowercase(),
+                    sector_id: node.sector_id.to_lowercase(),
+                }
+            })
+            .collect();
+
         StarMap {
             graph,
+            directed,
             id_to_index,
             natural_id_to_node,
+            stats,
+            degrees,
+            search_index,
+        }
+    }
+
+    /// Number of systems directly connected to this one
+    pub fn degree(&self, idx: NodeIndex) -> usize {
+        self.degrees[idx.index()]
+    }
+
+    /// Whether a connection is only listed by the system at `from`, not by the one at `to`
+    pub fn is_one_way(&self, from: NodeIndex, to: NodeIndex) -> bool {
+        !self.directed.contains_edge(to, from)
+    }
+
+    /// Find the route with the fewest jumps between two systems using a breadth-first search.
+    /// Returns `None` if the systems are not connected.
+    pub fn shortest_path(&self, from: NodeIndex, to: NodeIndex) -> Option<Vec<NodeIndex>> {
+        let mut previous: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(from);
+        queue.push_back(from);
+
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = vec![to];
+                let mut current = to;
+                while let Some(&prev) = previous.get(&current) {
+                    path.push(prev);
+                    current = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for neighbor in self.graph.neighbors(node) {
+                if visited.insert(neighbor) {
+                    previous.insert(neighbor, node);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Shortest path from each start to whichever of `targets` is closest to it, found with a
+    /// single breadth-first search outwards from all targets at once. Starts that can't reach
+    /// any target are left out. Each path runs from the start to the target.
+    pub fn paths_to_nearest(&self, starts: &[NodeIndex], targets: &[NodeIndex]) -> Vec<Vec<NodeIndex>> {
+        // Next step towards the nearest target for every reached system
+        let mut towards: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for &target in targets {
+            if towards.insert(target, target).is_none() {
+                queue.push_back(target);
+            }
+        }
+
+        while let Some(node) = queue.pop_front() {
+            for neighbor in self.graph.neighbors(node) {
+                if let std::collections::hash_map::Entry::Vacant(entry) = towards.entry(neighbor) {
+                    entry.insert(node);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        starts.iter()
+            .filter(|start| towards.contains_key(start))
+            .map(|&start| {
+                let mut path = vec![start];
+                let mut current = start;
+                while towards[&current] != current {
+                    current = towards[&current];
+                    path.push(current);
+                }
+                path
+            })
+            .collect()
+    }
+
+    /// Groups of systems that are connected to each other, largest first
+    pub fn connected_components(&self) -> &[Vec<NodeIndex>] {
+        &self.stats.components
+    }
+
+    /// Degree and approximate betweenness centrality of every system, most central first.
+    /// Betweenness is estimated from breadth-first searches out of `samples` evenly spread
+    /// systems (Brandes' algorithm), scaled up to the whole graph.
+    pub fn hub_scores(&self, samples: usize) -> Vec<HubScore> {
+        let node_count = self.graph.node_count();
+        let mut betweenness = vec![0.0f32; node_count];
+        let step = (node_count / samples.max(1)).max(1);
+        let sources: Vec<usize> = (0..node_count).step_by(step).collect();
+
+        for &source in &sources {
+            // Breadth-first search counting shortest paths to each system
+            let mut order = Vec::with_capacity(node_count);
+            let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); node_count];
+            let mut path_counts = vec![0.0f32; node_count];
+            let mut distance = vec![usize::MAX; node_count];
+            path_counts[source] = 1.0;
+            distance[source] = 0;
+            let mut queue = VecDeque::from([source]);
+            while let Some(node) = queue.pop_front() {
+                order.push(node);
+                for neighbor in self.graph.neighbors(NodeIndex::new(node)).map(|n| n.index()) {
+                    if distance[neighbor] == usize::MAX {
+                        distance[neighbor] = distance[node] + 1;
+                        queue.push_back(neighbor);
+                    }
+                    if distance[neighbor] == distance[node] + 1 {
+                        path_counts[neighbor] += path_counts[node];
+                        predecessors[neighbor].push(node);
+                    }
+                }
+            }
+
+            // Walk back from the furthest systems, accumulating each one's share of the paths
+            let mut dependency = vec![0.0f32; node_count];
+            for &node in order.iter().rev() {
+                for &predecessor in &predecessors[node] {
+                    dependency[predecessor] += path_counts[predecessor] / path_counts[node] * (1.0 + dependency[node]);
+                }
+                if node != source {
+                    betweenness[node] += dependency[node];
+                }
+            }
+        }
+
+        // Each path is found from both ends in an undirected graph
+        let scale = node_count as f32 / sources.len().max(1) as f32 / 2.0;
+        let mut scores: Vec<HubScore> = self.graph.node_indices()
+            .map(|node| HubScore {
+                node,
+                degree: self.degree(node),
+                betweenness: betweenness[node.index()] * scale,
+            })
+            .collect();
+        scores.sort_by(|a, b| b.betweenness.total_cmp(&a.betweenness).then(b.degree.cmp(&a.degree)));
+        scores
+    }
+
+    /// Chain shortest paths between consecutive waypoints into one route.
+    /// On failure returns the first pair of waypoints that aren't connected.
+    pub fn route_through(&self, waypoints: &[NodeIndex]) -> Result<Vec<NodeIndex>, (NodeIndex, NodeIndex)> {
+        let mut route: Vec<NodeIndex> = waypoints.first().copied().into_iter().collect();
+        for leg in waypoints.windows(2) {
+            let path = self.shortest_path(leg[0], leg[1]).ok_or((leg[0], leg[1]))?;
+            route.extend(path.into_iter().skip(1));
+        }
+        Ok(route)
+    }
+
+    /// Systems grouped by jump count from `from`, up to `max_depth` jumps. Layer 0 is `from` itself.
+    pub fn jump_layers(&self, from: NodeIndex, max_depth: usize) -> Vec<Vec<NodeIndex>> {
+        let mut visited = HashSet::new();
+        visited.insert(from);
+        let mut layers = vec![vec![from]];
+
+        while layers.len() <= max_depth {
+            let next: Vec<NodeIndex> = layers[layers.len() - 1].iter()
+                .flat_map(|&node| self.graph.neighbors(node))
+                .filter(|&neighbor| visited.insert(neighbor))
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            layers.push(next);
+        }
+
+        layers
+    }
+
+    /// Every system within `max_jumps` of `start`, with its jump count
+    pub fn reachable_within(&self, start: NodeIndex, max_jumps: usize) -> HashMap<NodeIndex, usize> {
+        self.jump_layers(start, max_jumps)
+            .into_iter()
+            .enumerate()
+            .flat_map(|(jumps, layer)| layer.into_iter().map(move |idx| (idx, jumps)))
+            .collect()
+    }
+
+    /// Interpolated world position of a ship along its flight at the given time
+    pub fn flight_position(&self, flight: &FlightPath, now_ms: f64) -> Option<[f32; 3]> {
+        let origin = &self.graph[*self.natural_id_to_node.get(&flight.origin_system_id)?];
+        let destination = &self.graph[*self.natural_id_to_node.get(&flight.destination_system_id)?];
+        let t = flight.progress(now_ms)?;
+
+        let mut position = [0.0; 3];
+        for (axis, value) in position.iter_mut().enumerate() {
+            *value = origin.position[axis] + (destination.position[axis] - origin.position[axis]) * t;
         }
+        Some(position)
     }
 
     pub fn node_count(&self) -> usize {
@@ -455,4 +1086,21 @@ impl StarMap {
     pub fn edge_count(&self) -> usize {
         self.graph.edge_count()
     }
+
+    /// Plain-text summary of the galaxy for sharing
+    pub fn summary_text(&self, cx_count: usize) -> String {
+        let mut text = format!(
+            "Systems: {}\nConnections: {}\nComponents: {}\nCommodity exchanges: {}\nStar types:",
+            self.node_count(),
+            self.edge_count(),
+            self.stats.components.len(),
+            cx_count,
+        );
+        for (star_type, count) in &self.stats.star_type_counts {
+            if *count > 0 {
+                text.push_str(&format!("\n  {:?}: {}", star_type, count));
+            }
+        }
+        text
+    }
 }
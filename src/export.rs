@@ -0,0 +1,116 @@
+use crate::data::StarMap;
+use petgraph::graph::NodeIndex;
+use wasm_bindgen::{Clamped, JsCast, JsValue};
+
+// How long a downloaded blob's URL is kept alive. Revoking it straight after the click can
+// cancel the download in some browsers.
+const BLOB_URL_REVOKE_DELAY_MS: u32 = 10_000;
+
+// Quote a CSV field if it contains a separator, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Serialize a route as CSV with one row per system and the cumulative distance travelled
+pub fn route_csv(star_map: &StarMap, route: &[NodeIndex]) -> String {
+    let mut csv = String::from("name,natural_id,star_type,sector,cumulative_distance\n");
+    let mut cumulative = 0.0;
+    let mut previous = None;
+
+    for &idx in route {
+        let node = &star_map.graph[idx];
+        if let Some(prev) = previous {
+            cumulative += star_map.graph[prev].distance_to(node);
+        }
+        previous = Some(idx);
+
+        csv.push_str(&format!(
+            "{},{},{:?},{},{:.2}\n",
+            csv_field(&node.name),
+            csv_field(&node.natural_id),
+            node.star_type,
+            csv_field(&node.sector_id),
+            cumulative,
+        ));
+    }
+
+    csv
+}
+
+/// Format a route for pasting into a spreadsheet: a summary line, then one tab-separated
+/// `natural_id	name` row per system
+pub fn route_text(star_map: &StarMap, route: &[NodeIndex]) -> String {
+    let distance: f32 = route.windows(2)
+        .map(|pair| star_map.graph[pair[0]].distance_to(&star_map.graph[pair[1]]))
+        .sum();
+    let mut text = format!("{} jumps, {:.1} units\n", route.len().saturating_sub(1), distance);
+    for &idx in route {
+        let node = &star_map.graph[idx];
+        text.push_str(&format!("{}\t{}\n", node.natural_id, node.name));
+    }
+    text
+}
+
+/// Start writing text to the clipboard. Call this while handling the user's click, since
+/// browsers only allow clipboard writes then. The returned future fails if access was denied.
+pub fn copy_to_clipboard(text: &str) -> Result<wasm_bindgen_futures::JsFuture, JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("No window object"))?;
+    Ok(wasm_bindgen_futures::JsFuture::from(window.navigator().clipboard().write_text(text)))
+}
+
+/// Trigger a browser download of the given text contents
+pub fn download_text(filename: &str, mime_type: &str, contents: &str) -> Result<(), JsValue> {
+    let parts = js_sys::Array::new();
+    parts.push(&JsValue::from_str(contents));
+
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+    download_url(filename, &url)?;
+    gloo_timers::callback::Timeout::new(BLOB_URL_REVOKE_DELAY_MS, move || {
+        let _ = web_sys::Url::revoke_object_url(&url);
+    })
+    .forget();
+    Ok(())
+}
+
+/// Trigger a browser download of an image as PNG. The browser does the encoding via a canvas.
+pub fn download_png(filename: &str, image: &egui::ColorImage) -> Result<(), JsValue> {
+    let [width, height] = image.size;
+    let rgba: Vec<u8> = image.pixels.iter()
+        .flat_map(|pixel| pixel.to_srgba_unmultiplied())
+        .collect();
+
+    let canvas: web_sys::HtmlCanvasElement = document()?.create_element("canvas")?.dyn_into()?;
+    canvas.set_width(width as u32);
+    canvas.set_height(height as u32);
+    let context: web_sys::CanvasRenderingContext2d = canvas
+        .get_context("2d")?
+        .ok_or("No 2d canvas context")?
+        .dyn_into()?;
+    let image_data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(Clamped(&rgba), width as u32, height as u32)?;
+    context.put_image_data(&image_data, 0.0, 0.0)?;
+
+    download_url(filename, &canvas.to_data_url_with_type("image/png")?)
+}
+
+fn document() -> Result<web_sys::Document, JsValue> {
+    web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| JsValue::from_str("No document object"))
+}
+
+// Download a URL through a temporary <a download> element
+fn download_url(filename: &str, url: &str) -> Result<(), JsValue> {
+    let anchor: web_sys::HtmlAnchorElement = document()?.create_element("a")?.dyn_into()?;
+    anchor.set_href(url);
+    anchor.set_download(filename);
+    anchor.click();
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use crate::data::StarSystem;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+/// Ask the user to pick a file and read it as text. Returns `None` if the dialog was dismissed.
+pub async fn pick_text_file(accept: &str) -> Result<Option<String>, JsValue> {
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| JsValue::from_str("No document object"))?;
+    let input: web_sys::HtmlInputElement = document.create_element("input")?.dyn_into()?;
+    input.set_type("file");
+    input.set_accept(accept);
+
+    // Settles once a file is chosen or the dialog is cancelled
+    let picked = js_sys::Promise::new(&mut |resolve, _reject| {
+        let resolve_cancel = resolve.clone();
+        let on_change = Closure::once_into_js(move || {
+            let _ = resolve.call0(&JsValue::NULL);
+        });
+        let on_cancel = Closure::once_into_js(move || {
+            let _ = resolve_cancel.call0(&JsValue::NULL);
+        });
+        input.set_onchange(Some(on_change.unchecked_ref()));
+        let _ = input.add_event_listener_with_callback("cancel", on_cancel.unchecked_ref());
+    });
+    input.click();
+    JsFuture::from(picked).await?;
+
+    let Some(file) = input.files().and_then(|files| files.get(0)) else {
+        return Ok(None);
+    };
+    let text = JsFuture::from(file.text()).await?;
+    Ok(text.as_string())
+}
+
+/// Parse a `/systemstars` JSON dump, skipping malformed records like the API client does.
+/// Returns the systems that parsed plus a count of skipped records.
+pub fn parse_star_systems(json: &str) -> Result<(Vec<StarSystem>, usize), String> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|e| format!("Not valid JSON: {}", e))?;
+    let serde_json::Value::Array(records) = value else {
+        return Err("Expected a JSON array of star systems".to_string());
+    };
+
+    let mut systems = Vec::new();
+    let mut skipped = 0;
+    for (i, record) in records.into_iter().enumerate() {
+        match serde_json::from_value(record) {
+            Ok(system) => systems.push(system),
+            Err(e) => {
+                tracing::warn!("Skipping malformed star system record {}: {}", i, e);
+                skipped += 1;
+            }
+        }
+    }
+
+    if systems.is_empty() {
+        return Err(format!("No valid star systems found ({} records skipped)", skipped));
+    }
+    Ok((systems, skipped))
+}
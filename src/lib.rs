@@ -1,90 +1,763 @@
 mod api;
 mod data;
+mod export;
+mod import;
 
-use data::{BaseProduction, FlightPath, MaterialRate, StarMap, StarNode, SystemMarker, UserData};
+use api::ApiError;
+use data::{
+    BaseProduction, Bounds, ExchangePrice, FlightPath, HubScore, MaterialRate, Planet, ResourceDeposit, Ship, StarMap, StarNode, StarType, SystemMarker,
+    SystemResources,
+    TravelTimeEstimate, UserData,
+};
 use eframe::egui;
 use petgraph::graph::NodeIndex;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use wasm_bindgen::prelude::*;
 
 const AUTH_TOKEN_KEY: &str = "fio_auth_token";
 const USERNAME_KEY: &str = "fio_username";
+const AUTH_EXPIRY_KEY: &str = "fio_auth_expiry";
+const LAST_SEEN_VERSION_KEY: &str = "last_seen_version";
+const DISPLAY_SETTINGS_KEY: &str = "display_settings";
+const SELECTED_STAR_KEY: &str = "selected_star";
+const MAP_VIEW_STATE_KEY: &str = "map_view_state";
+const STAR_SYSTEMS_CACHE_KEY: &str = "star_systems_cache";
+const API_BASE_URL_KEY: &str = "api_base_url";
+
+// How long cached star systems are used before fetching them again
+const STAR_SYSTEMS_CACHE_TTL_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+const RELEASE_NOTES: &str = include_str!("../CHANGELOG.md");
+
+// Brightness factor applied to stars and connections outside the focus region
+const FOCUS_DIM_FACTOR: f32 = 0.15;
+
+// Brightness of stars beyond the jump range, and of the furthest stars within it
+const OUT_OF_RANGE_DIM_FACTOR: f32 = 0.08;
+const RANGE_EDGE_BRIGHTNESS: f32 = 0.4;
+
+// Number of jump layers outlined by the reach overlay
+const REACH_HULL_DEPTH: usize = 3;
+
+// Brightness factor applied to connections that aren't part of the active route
+const ROUTE_DIM_FACTOR: f32 = 0.1;
+
+// While panning with sparse connections enabled, only every Nth connection is drawn
+const DRAG_CONNECTION_STRIDE: usize = 8;
+
+// In nearest-star click mode, clicks further than this (in screen pixels) from any star select nothing
+const NEAREST_CLICK_RADIUS: f32 = 40.0;
+
+// Fading dots drawn behind in-flight ships, and their spacing in screen pixels
+const SHIP_TRAIL_DOTS: usize = 10;
+const SHIP_TRAIL_SPACING: f32 = 5.0;
+
+// Width of a full-length bar in the spectral type histogram
+const STAT_BAR_WIDTH: f32 = 120.0;
+
+// How close (in screen pixels) the cursor must be to a connection to highlight it
+const EDGE_HOVER_RADIUS: f32 = 4.0;
+
+// Default width and opacity of connection lines, both adjustable in the sidebar
+const DEFAULT_CONNECTION_WIDTH: f32 = 0.5;
+const DEFAULT_CONNECTION_OPACITY: f32 = 0.3;
+
+// How far curved connections bow away from the straight line, relative to their length
+const CONNECTION_CURVE_BOW: f32 = 0.12;
+
+// Opacity of the per-sector outlines and filled regions
+const SECTOR_OUTLINE_ALPHA: f32 = 0.25;
+const SECTOR_FILL_ALPHA: f32 = 0.06;
+
+// Size of the minimap overlay and its distance from the corner of the map
+const MINIMAP_SIZE: egui::Vec2 = egui::vec2(180.0, 135.0);
+const MINIMAP_MARGIN: f32 = 10.0;
+
+// Preferred on-screen length of the scale bar, and its distance from the corner of the map
+const SCALE_BAR_TARGET_PX: f32 = 120.0;
+const SCALE_BAR_MARGIN: f32 = 16.0;
+
+// Smallest on-screen spacing between background grid lines
+const GRID_MIN_SPACING_PX: f32 = 60.0;
+// Upper bound on grid lines per axis, in case float precision makes the step tiny
+const GRID_MAX_LINES: i64 = 300;
+
+// How long a failed "Go to ID" lookup shows its error, in seconds
+const GOTO_ERROR_SECS: f64 = 2.0;
+
+// How many jumps out the "Nearby systems" list reaches
+const NEARBY_JUMPS: usize = 3;
+
+// Most search results listed at once
+const MAX_SEARCH_RESULTS: usize = 100;
+
+// Number of hubs listed and highlighted, and the search sources used to estimate betweenness
+const HUB_COUNT: usize = 10;
+const HUB_BETWEENNESS_SAMPLES: usize = 64;
+
+// Refresh the auth token this long before it expires
+const AUTH_REFRESH_MARGIN_MS: f64 = 10.0 * 60.0 * 1000.0;
+// Wait this long before retrying a failed refresh
+const AUTH_REFRESH_RETRY_MS: f64 = 60.0 * 1000.0;
 
 fn get_local_storage() -> Option<web_sys::Storage> {
     web_sys::window()?.local_storage().ok()?
 }
 
-fn save_auth(token: &str, username: &str) {
+fn save_auth(token: &str, username: &str, expiry: Option<&str>) {
     if let Some(storage) = get_local_storage() {
         let _ = storage.set_item(AUTH_TOKEN_KEY, token);
         let _ = storage.set_item(USERNAME_KEY, username);
+        match expiry {
+            Some(expiry) => {
+                let _ = storage.set_item(AUTH_EXPIRY_KEY, expiry);
+            }
+            None => {
+                let _ = storage.remove_item(AUTH_EXPIRY_KEY);
+            }
+        }
     }
 }
 
-fn load_auth() -> Option<(String, String)> {
+fn load_auth() -> Option<(String, String, Option<String>)> {
     let storage = get_local_storage()?;
     let token = storage.get_item(AUTH_TOKEN_KEY).ok()??;
     let username = storage.get_item(USERNAME_KEY).ok()??;
-    Some((token, username))
+    let expiry = storage.get_item(AUTH_EXPIRY_KEY).ok().flatten();
+    Some((token, username, expiry))
 }
 
 fn clear_auth() {
     if let Some(storage) = get_local_storage() {
         let _ = storage.remove_item(AUTH_TOKEN_KEY);
         let _ = storage.remove_item(USERNAME_KEY);
+        let _ = storage.remove_item(AUTH_EXPIRY_KEY);
+    }
+}
+
+fn load_api_base_url() -> Option<String> {
+    get_local_storage()?.get_item(API_BASE_URL_KEY).ok()?
+}
+
+fn save_api_base_url(base_url: &str) {
+    if let Some(storage) = get_local_storage() {
+        let _ = match base_url.trim() {
+            "" => storage.remove_item(API_BASE_URL_KEY),
+            base_url => storage.set_item(API_BASE_URL_KEY, base_url),
+        };
+    }
+}
+
+fn load_last_seen_version() -> Option<String> {
+    get_local_storage()?.get_item(LAST_SEEN_VERSION_KEY).ok()?
+}
+
+fn save_last_seen_version() {
+    if let Some(storage) = get_local_storage() {
+        let _ = storage.set_item(LAST_SEEN_VERSION_KEY, APP_VERSION);
+    }
+}
+
+// The persisted part of the map view
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct ViewState {
+    offset: [f32; 2],
+    zoom: f32,
+    projection: Projection,
+    #[serde(default)]
+    yaw: f32,
+    #[serde(default)]
+    pitch: f32,
+}
+
+// Corrupt or out-of-range stored values are ignored so the default view is used instead
+fn load_view_state() -> Option<ViewState> {
+    let json = get_local_storage()?.get_item(MAP_VIEW_STATE_KEY).ok()??;
+    let state: ViewState = serde_json::from_str(&json).ok()?;
+    let valid = state.offset.iter().all(|v| v.is_finite()) && (MIN_ZOOM..=MAX_ZOOM).contains(&state.zoom);
+    valid.then_some(state)
+}
+
+fn save_view_state(state: &ViewState) {
+    if let (Some(storage), Ok(json)) = (get_local_storage(), serde_json::to_string(state)) {
+        let _ = storage.set_item(MAP_VIEW_STATE_KEY, &json);
+    }
+}
+
+fn load_selected_star() -> Option<String> {
+    get_local_storage()?.get_item(SELECTED_STAR_KEY).ok()?
+}
+
+fn save_selected_star(natural_id: Option<&str>) {
+    if let Some(storage) = get_local_storage() {
+        let _ = match natural_id {
+            Some(natural_id) => storage.set_item(SELECTED_STAR_KEY, natural_id),
+            None => storage.remove_item(SELECTED_STAR_KEY),
+        };
+    }
+}
+
+// URL query parameter holding the selected system, for shareable links
+const SYSTEM_QUERY_PARAM: &str = "system";
+
+fn current_url() -> Option<web_sys::Url> {
+    let href = web_sys::window()?.location().href().ok()?;
+    web_sys::Url::new(&href).ok()
+}
+
+fn load_system_from_url() -> Option<String> {
+    current_url()?.search_params().get(SYSTEM_QUERY_PARAM).filter(|id| !id.is_empty())
+}
+
+// Reflect the selection in the address bar without adding a history entry
+fn save_system_to_url(natural_id: Option<&str>) {
+    let (Some(window), Some(url)) = (web_sys::window(), current_url()) else {
+        return;
+    };
+    match natural_id {
+        Some(natural_id) => url.search_params().set(SYSTEM_QUERY_PARAM, natural_id),
+        None => url.search_params().delete(SYSTEM_QUERY_PARAM),
+    }
+    if let Ok(history) = window.history() {
+        let _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&url.href()));
+    }
+}
+
+// Display preferences persisted across sessions. Missing fields fall back to defaults
+// so older stored settings keep loading as new options are added.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct DisplaySettings {
+    label_content: LabelContent,
+    compact_markers: bool,
+    click_nearest_star: bool,
+    color_blind_markers: bool,
+    show_connections: bool,
+    show_labels: bool,
+    show_cx: bool,
+    show_bases: bool,
+    show_warehouses: bool,
+    show_ships: bool,
+    only_marked_systems: bool,
+    only_marked_connections: bool,
+    show_grid: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            label_content: LabelContent::default(),
+            compact_markers: false,
+            click_nearest_star: false,
+            color_blind_markers: false,
+            show_connections: true,
+            show_labels: false,
+            show_cx: true,
+            show_bases: true,
+            show_warehouses: true,
+            show_ships: true,
+            only_marked_systems: false,
+            only_marked_connections: true,
+            show_grid: false,
+        }
+    }
+}
+
+fn load_display_settings() -> DisplaySettings {
+    get_local_storage()
+        .and_then(|storage| storage.get_item(DISPLAY_SETTINGS_KEY).ok()?)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_display_settings(settings: &DisplaySettings) {
+    if let (Some(storage), Ok(json)) = (get_local_storage(), serde_json::to_string(settings)) {
+        let _ = storage.set_item(DISPLAY_SETTINGS_KEY, &json);
+    }
+}
+
+fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+const MS_PER_MINUTE: f64 = 60_000.0;
+const MS_PER_HOUR: f64 = 3_600_000.0;
+
+// Format a byte count in KB or MB (e.g. "812 KB", "3.4 MB")
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1_000_000 {
+        format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+    } else {
+        format!("{} KB", bytes / 1_000)
+    }
+}
+
+// Format a duration as a short human-readable string (e.g. "142 days", "2h 14m")
+fn format_duration(ms: f64) -> String {
+    let ms = ms.max(0.0);
+    if ms >= MS_PER_DAY {
+        let days = (ms / MS_PER_DAY).floor() as u64;
+        if days == 1 {
+            "1 day".to_string()
+        } else {
+            format!("{} days", days)
+        }
+    } else if ms >= MS_PER_HOUR {
+        let hours = (ms / MS_PER_HOUR).floor() as u64;
+        let minutes = ((ms % MS_PER_HOUR) / MS_PER_MINUTE).floor() as u64;
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", (ms / MS_PER_MINUTE).floor() as u64)
+    }
+}
+
+// "arriving in 2h 14m", or "arrived" once the arrival time has passed
+fn arrival_text(flight: &FlightPath, now_ms: f64) -> Option<String> {
+    let remaining = flight.remaining_ms(now_ms)?;
+    Some(if remaining <= 0.0 {
+        "arrived".to_string()
+    } else {
+        format!("arriving in {}", format_duration(remaining))
+    })
+}
+
+// Parse the API's expiry timestamp into epoch milliseconds
+fn parse_expiry(expiry: &str) -> Option<f64> {
+    let ms = js_sys::Date::parse(expiry);
+    if ms.is_nan() {
+        None
+    } else {
+        Some(ms)
+    }
+}
+
+// Everything system markers and supply lanes are built from, so a rebuild can be skipped
+// when nothing they depend on actually changed
+struct MarkerInputs {
+    star_map: Option<Arc<StarMap>>,
+    toggles: [bool; 6],
+    cx_system_ids: HashSet<String>,
+    station_systems: HashMap<String, String>,
+    base_system_ids: HashSet<String>,
+    ship_system_ids: HashSet<String>,
+    warehouse_locations: HashSet<String>,
+    in_system_flight_origins: HashSet<String>,
+}
+
+impl PartialEq for MarkerInputs {
+    fn eq(&self, other: &Self) -> bool {
+        // The map is compared by identity; a reload always rebuilds
+        let same_map = match (&self.star_map, &other.star_map) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        same_map
+            && self.toggles == other.toggles
+            && self.cx_system_ids == other.cx_system_ids
+            && self.station_systems == other.station_systems
+            && self.base_system_ids == other.base_system_ids
+            && self.ship_system_ids == other.ship_system_ids
+            && self.warehouse_locations == other.warehouse_locations
+            && self.in_system_flight_origins == other.in_system_flight_origins
     }
 }
 
 pub struct StarMapApp {
     star_map: Option<Arc<StarMap>>,
     loading: bool,
+    loading_started_ms: f64,
+    star_download: Option<api::DownloadProgress>, // None until the response body starts arriving
+    star_systems_requested: bool,
+    bypass_star_cache: bool, // Next star system load ignores (and overwrites) the cache
+    star_file_requested: bool, // Pick a systemstars JSON file to load instead of fetching
+    offline_star_data: bool, // Star systems came from a file; late network results are ignored
+    import_error: Option<String>,
     error: Option<String>,
+    skipped_systems: usize,
     view: MapView,
+    saved_view_state: Option<ViewState>,
+    view_saved_at: f64, // egui time of the last view state write
+    map_size: egui::Vec2,
+    map_rect: egui::Rect,
+    screenshot_pending: bool, // Waiting for a requested screenshot to save as an image
     selected_star: Option<NodeIndex>,
+    tab_cycle: Option<(NodeIndex, usize)>, // Star whose neighbors Tab is cycling through, and the current one
+    pending_selection: Option<String>, // natural_id to select once the map has loaded
+    linked_selection: Option<String>, // natural_id from a ?system= link, tried before pending_selection
+    persisted_selection: Option<NodeIndex>, // last selection written to storage
+    selected_stars: HashSet<NodeIndex>,
     hovered_star: Option<NodeIndex>,
+    route: Option<Vec<NodeIndex>>,
+    unreachable_route: Option<(NodeIndex, NodeIndex)>, // Last routing attempt between disconnected systems
+    waypoints: Vec<NodeIndex>, // Ordered stops of a multi-leg route
+    route_copy_pending: Option<(String, wasm_bindgen_futures::JsFuture)>, // Route text and its clipboard write
+    route_export_fallback: Option<String>, // Route text shown for manual copying when the clipboard failed
+    dim_off_route_connections: bool,
+    show_reach_hulls: bool,
+    show_jump_range: bool,
+    jump_range: usize,
+    jump_range_counts: HashMap<NodeIndex, usize>, // Jump counts within range of the selection
+    jump_range_source: Option<(NodeIndex, usize)>, // Selection and range the cached counts belong to
+    nearest_exchanges: Vec<(usize, NodeIndex)>, // Exchanges by jump count from the selected base system
+    nearest_exchanges_source: Option<NodeIndex>, // Selection the cached exchanges belong to
+    
+    // Distances from a chosen reference system, appended to labels
+    reference_star: Option<NodeIndex>,
+    measure_from: Option<NodeIndex>, // Other end of the straight-line measurement to the selected star
+    reference_metric: DistanceMetric,
+    reference_jumps: HashMap<NodeIndex, usize>,
+    reference_jumps_source: Option<NodeIndex>, // Reference the cached jump counts belong to
+    following_ship: Option<String>, // ship_id
+    range_ship: Option<String>, // ship_id whose estimated FTL range is drawn
+    focus_center: Option<NodeIndex>,
+    focus_radius: f32,
     search_query: String,
+    last_search_query: String,
+    previous_frame_query: String, // Query as typed last frame, for debouncing
+    goto_query: String,
+    goto_error: Option<(String, f64)>, // Message and the time it was shown, cleared after a moment
+    search_matches: Vec<NodeIndex>,
+    sort_search_by_degree: bool,
     show_connections: bool,
+    sparse_connections_while_dragging: bool,
+    curved_connections: bool,
+    directed_connections: bool,
+    connection_width: f32,
+    connection_opacity: f32,
+    scale_connections_with_zoom: bool, // Treat connection_width as the width at zoom 1
+    color_by_sector: bool,
+    show_sectors: bool,
+    highlighted_star_types: HashSet<StarType>, // Picked in the star type legend
+    show_minimap: bool,
+    show_hubs: bool,
+    hub_scores: Option<Vec<HubScore>>, // Computed on first use, most central first
+    visible_sectors: Vec<String>, // Sectors with a star on screen last frame, for the legend
     show_labels: bool,
+    label_content: LabelContent,
+    show_diagnostics: bool,
+    highlight_suspicious: bool,
+    animate_projection: bool,
+    normalize_axes: bool,
     
     // Authentication
     auth_token: Option<String>,
+    auth_expiry_ms: Option<f64>,
+    refreshing_auth: bool,
+    next_auth_refresh_ms: f64,
     username: String,
     password: String,
     login_error: Option<String>,
-    logging_in: bool,
+    login_state: LoginState,
     
     // User data
     user_data: Option<UserData>,
     loading_user_data: bool,
     
+    // Ship list
+    ship_sort: ShipSort,
+    ship_min_age_days: u32,
+    ship_system_filter: Option<String>, // Only list ships docked in this system
+    reveal_ship_list: bool, // Expand the ship list on the next frame
+    
     // Exchange stations (public data)
     cx_system_ids: HashSet<String>,
     cx_names: HashMap<String, String>, // system_id -> CX name
+    station_systems: HashMap<String, String>, // station natural_id -> system natural_id
     
     // System markers (computed from CX + user data) - now stores all markers per system
     system_markers: HashMap<String, Vec<SystemMarker>>,
+    show_supply_lanes: bool,
+    supply_lanes: Vec<Vec<NodeIndex>>, // Path from each base system to its nearest CX, rebuilt with the markers
+    markers_dirty: bool, // Inputs may have changed; checked once before the next frame is drawn
+    marker_inputs: Option<MarkerInputs>, // What the current markers were built from
     
     // Show markers toggle
     show_cx: bool,
+    hide_cx_in_base_systems: bool, // Only mark exchanges outside systems with our bases
     show_bases: bool,
+    show_warehouses: bool,
     show_ships: bool,
+    only_marked_systems: bool, // Hide stars without a marker
+    only_marked_connections: bool, // With only_marked_systems, also hide connections touching unmarked stars
+    show_grid: bool,
+    compact_markers: bool,
+    click_nearest_star: bool, // Clicks select the closest star instead of only the hovered one
+    color_blind_markers: bool, // Color-blind-friendly marker colors and dashed rings
+    show_inter_system_flights: bool,
+    show_in_system_flights: bool,
+    show_ship_trails: bool,
+    show_arrival_labels: bool, // Time to arrival next to ships in flight
+    
+    // Planet resources aggregated per system (system natural_id -> ticker -> deposits)
+    system_resources: HashMap<String, SystemResources>,
+    resource_tickers: Vec<String>,
+    resources_requested: bool,
+    loading_resources: bool,
+    resource_error: Option<String>,
+    resource_filter: Option<String>,
+    
+    // Material prices by exchange code, fetched for all exchanges on first use
+    exchange_prices: Option<HashMap<String, Vec<ExchangePrice>>>, // By exchange code; None until loaded
+    exchange_prices_requested: bool,
+    loading_exchange_prices: bool,
+    exchange_prices_error: Option<String>,
+    price_filter: String,
+    
+    // Planets per system, fetched lazily for selected systems
+    system_planets: HashMap<String, Vec<Planet>>,
+    planets_requested: HashSet<String>, // Systems already queued or fetched
+    pending_planet_requests: Vec<String>, // Newly requested systems, picked up by AppWrapper
+    planet_errors: HashMap<String, String>, // Last fetch error per system
+    
+    // FIO API mirror; blank means the public API
+    api_base_url: String,
+    
+    // "What's new" window, shown once per new version
+    show_whats_new: bool,
     
     // Production window state - which planets' production windows are open (by planet_natural_id)
     production_windows_open: HashSet<String>,
 }
 
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+enum LabelContent {
+    #[default]
+    Name,
+    NaturalId,
+    Both,
+}
+
+impl LabelContent {
+    fn label(self) -> &'static str {
+        match self {
+            LabelContent::Name => "Name",
+            LabelContent::NaturalId => "ID",
+            LabelContent::Both => "ID and name",
+        }
+    }
+}
+
+// Progress of a login: the button requests one, and AppWrapper sends it exactly once
+#[derive(Clone, Copy, PartialEq)]
+enum LoginState {
+    Idle,
+    Requested,
+    InFlight,
+}
+
+// Which neighbor keyboard navigation moves to
+#[derive(Clone, Copy, PartialEq)]
+enum NeighborTarget {
+    Next,
+    Direction(egui::Vec2),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DistanceMetric {
+    Jumps,
+    Euclidean,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ShipSort {
+    Registration,
+    Newest,
+    Oldest,
+}
+
+// Duration of the animated transition between projections
+const PROJECTION_TRANSITION_SECS: f64 = 0.4;
+
+// Minimum time between writes of the map view to storage
+const VIEW_SAVE_INTERVAL_SECS: f64 = 1.0;
+
+// Offer a retry when loading the star map takes longer than this
+const SLOW_LOAD_WARNING_MS: f64 = 10_000.0;
+// Downloads past this size get a note that the star catalog is large
+const LARGE_DOWNLOAD_BYTES: u64 = 1_000_000;
+
+// How quickly an animated zoom closes in on its target (per second)
+const ZOOM_SMOOTHING: f32 = 12.0;
+
+// Keyboard panning speed in screen pixels per second
+const KEYBOARD_PAN_SPEED: f32 = 600.0;
+
+// Zoom level a double-clicked star is brought to, unless already zoomed in further
+const DOUBLE_CLICK_ZOOM: f32 = 2.0;
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 5.0;
+
 struct MapView {
     offset: egui::Vec2,
     zoom: f32,
     projection: Projection,
+    
+    // Projection morph state: the layout being blended from, the projection that was
+    // last drawn (to detect changes), and how far the blend has progressed (0..=1)
+    from_projection: Projection,
+    shown_projection: Projection,
+    transition_start: f64,
+    projection_blend: f32,
+    
+    // Optional per-axis rescaling so all axes span the same range
+    normalization: Option<AxisNormalization>,
+    
+    // Zoom level being eased towards, if any
+    zoom_target: Option<ZoomTarget>,
+    
+    // Star being panned and zoomed to, if any
+    fly_target: Option<FlyTarget>,
+    
+    // Camera used by the 3D projection
+    orbit: Orbit,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy)]
+struct ZoomTarget {
+    zoom: f32,
+    pivot: egui::Vec2, // Screen point (relative to the map center) that stays fixed while zooming
+}
+
+#[derive(Clone, Copy)]
+struct FlyTarget {
+    position: [f32; 3], // World position that ends up in the center of the map
+    zoom: f32,
+}
+
+// Scales each axis around the galaxy center so that all axes span the largest axis' range.
+// This deliberately distorts true distances.
+#[derive(Clone, Copy)]
+struct AxisNormalization {
+    center: [f32; 3],
+    scale: [f32; 3],
+}
+
+impl AxisNormalization {
+    fn from_bounds(bounds: &data::Bounds) -> Self {
+        let size = bounds.size();
+        let common = size[0].max(size[1]).max(size[2]);
+        AxisNormalization {
+            center: std::array::from_fn(|axis| (bounds.min[axis] + bounds.max[axis]) * 0.5),
+            scale: std::array::from_fn(|axis| {
+                if size[axis] > f32::EPSILON { common / size[axis] } else { 1.0 }
+            }),
+        }
+    }
+    
+    fn apply(&self, position: [f32; 3]) -> [f32; 3] {
+        let mut result = position;
+        for (axis, value) in result.iter_mut().enumerate() {
+            *value = self.center[axis] + (*value - self.center[axis]) * self.scale[axis];
+        }
+        result
+    }
+    
+    fn invert(&self, axis: usize, value: f32) -> f32 {
+        self.center[axis] + (value - self.center[axis]) / self.scale[axis]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 enum Projection {
     XY,
     XZ,
     YZ,
+    Perspective3D,
+}
+
+impl Projection {
+    fn project(self, position: [f32; 3], orbit: &Orbit) -> (f32, f32) {
+        match self {
+            Projection::XY => (position[0], position[1]),
+            Projection::XZ => (position[0], position[2]),
+            Projection::YZ => (position[1], position[2]),
+            Projection::Perspective3D => {
+                let (x, y, _, scale) = orbit.transform(position);
+                (orbit.center[0] + x * scale, orbit.center[1] + y * scale)
+            }
+        }
+    }
+    
+    // World axes shown horizontally and vertically, for the flat projections
+    fn axes(self) -> Option<[usize; 2]> {
+        match self {
+            Projection::XY => Some([0, 1]),
+            Projection::XZ => Some([0, 2]),
+            Projection::YZ => Some([1, 2]),
+            Projection::Perspective3D => None,
+        }
+    }
+    
+    // Short name used in file names
+    fn file_name(self) -> &'static str {
+        match self {
+            Projection::XY => "xy",
+            Projection::XZ => "xz",
+            Projection::YZ => "yz",
+            Projection::Perspective3D => "3d",
+        }
+    }
+    
+    fn next(self) -> Self {
+        match self {
+            Projection::XY => Projection::XZ,
+            Projection::XZ => Projection::YZ,
+            Projection::YZ => Projection::Perspective3D,
+            Projection::Perspective3D => Projection::XY,
+        }
+    }
+}
+
+// Radians of rotation per pixel dragged in the 3D view
+const ORBIT_DRAG_SPEED: f32 = 0.01;
+
+// Camera for the 3D view, orbiting the galaxy center. With no rotation it looks down the
+// Z axis, matching the X-Y projection.
+#[derive(Clone, Copy)]
+struct Orbit {
+    yaw: f32,
+    pitch: f32,
+    center: [f32; 3],
+    distance: f32, // From the camera to the center, in world units
+}
+
+impl Default for Orbit {
+    fn default() -> Self {
+        Orbit {
+            yaw: 0.0,
+            pitch: 0.0,
+            center: [0.0; 3],
+            distance: 1000.0,
+        }
+    }
+}
+
+impl Orbit {
+    // Rotate a position around the center. Returns the rotated x and y relative to the center,
+    // the depth (positive is towards the camera) and the perspective scale at that depth.
+    fn transform(&self, position: [f32; 3]) -> (f32, f32, f32, f32) {
+        let [x, y, z] = std::array::from_fn(|axis| position[axis] - self.center[axis]);
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        
+        let x1 = x * cos_yaw - z * sin_yaw;
+        let z1 = x * sin_yaw + z * cos_yaw;
+        let y2 = y * cos_pitch - z1 * sin_pitch;
+        let z2 = y * sin_pitch + z1 * cos_pitch;
+        
+        let scale = self.distance / (self.distance - z2).max(self.distance * 0.1);
+        (x1, y2, z2, scale)
+    }
+    
+    fn rotate(&mut self, delta: egui::Vec2) {
+        self.yaw += delta.x * ORBIT_DRAG_SPEED;
+        self.pitch = (self.pitch + delta.y * ORBIT_DRAG_SPEED)
+            .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
+    }
 }
 
 impl Default for MapView {
@@ -93,81 +766,680 @@ impl Default for MapView {
             offset: egui::Vec2::ZERO,
             zoom: 0.3,
             projection: Projection::XY,
+            from_projection: Projection::XY,
+            shown_projection: Projection::XY,
+            transition_start: 0.0,
+            projection_blend: 1.0,
+            normalization: None,
+            zoom_target: None,
+            fly_target: None,
+            orbit: Orbit::default(),
         }
     }
 }
 
-impl Default for StarMapApp {
-    fn default() -> Self {
-        Self {
-            star_map: None,
-            loading: false,
-            error: None,
-            view: MapView::default(),
-            selected_star: None,
-            hovered_star: None,
-            search_query: String::new(),
-            show_connections: true,
-            show_labels: false,
-            
-            auth_token: None,
-            username: String::new(),
-            password: String::new(),
-            login_error: None,
-            logging_in: false,
-            
-            user_data: None,
-            loading_user_data: false,
-            
-            cx_system_ids: HashSet::new(),
-            cx_names: HashMap::new(),
-            system_markers: HashMap::new(),
-            
-            show_cx: true,
-            show_bases: true,
-            show_ships: true,
-            
-            production_windows_open: HashSet::new(),
+impl MapView {
+    fn from_state(state: ViewState) -> Self {
+        MapView {
+            offset: egui::vec2(state.offset[0], state.offset[1]),
+            zoom: state.zoom,
+            projection: state.projection,
+            from_projection: state.projection,
+            shown_projection: state.projection,
+            orbit: Orbit {
+                yaw: state.yaw,
+                pitch: state.pitch,
+                ..Orbit::default()
+            },
+            ..MapView::default()
         }
     }
-}
-
-impl StarMapApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Self::default()
+    
+    fn state(&self) -> ViewState {
+        ViewState {
+            offset: [self.offset.x, self.offset.y],
+            zoom: self.zoom,
+            projection: self.projection,
+            yaw: self.orbit.yaw,
+            pitch: self.orbit.pitch,
+        }
     }
-
-    fn update_system_markers(&mut self) {
-        self.system_markers.clear();
+    
+    // Start a morph whenever the projection changed since the last frame and advance the blend
+    fn update_projection_transition(&mut self, now: f64, animate: bool) {
+        if self.projection != self.shown_projection {
+            self.from_projection = self.shown_projection;
+            self.shown_projection = self.projection;
+            self.transition_start = now;
+        }
         
-        // Collect all system IDs that have any marker
-        let mut all_system_ids: HashSet<String> = HashSet::new();
+        self.projection_blend = if animate {
+            ((now - self.transition_start) / PROJECTION_TRANSITION_SECS).clamp(0.0, 1.0) as f32
+        } else {
+            1.0
+        };
+    }
+    
+    fn is_transitioning(&self) -> bool {
+        self.projection_blend < 1.0
+    }
+    
+    // Start (or extend) an animated zoom by the given factor around a screen point
+    fn zoom_by(&mut self, factor: f32, pivot: egui::Vec2) {
+        self.fly_target = None;
+        let from = self.zoom_target.map_or(self.zoom, |target| target.zoom);
+        self.zoom_target = Some(ZoomTarget {
+            zoom: (from * factor).clamp(MIN_ZOOM, MAX_ZOOM),
+            pivot,
+        });
+    }
+    
+    // Ease the zoom towards its target, keeping the pivot point in place
+    fn update_zoom_animation(&mut self, dt: f32) {
+        let Some(target) = self.zoom_target else {
+            return;
+        };
         
-        if self.show_cx {
-            all_system_ids.extend(self.cx_system_ids.iter().cloned());
+        let mut zoom = self.zoom + (target.zoom - self.zoom) * (1.0 - (-dt * ZOOM_SMOOTHING).exp());
+        if (zoom - target.zoom).abs() < target.zoom * 1e-3 {
+            zoom = target.zoom;
+            self.zoom_target = None;
         }
-        
-        if let Some(user_data) = &self.user_data {
-            if self.show_bases {
-                all_system_ids.extend(user_data.base_system_ids.iter().cloned());
-            }
+        self.zoom_about(zoom, target.pivot);
+    }
+    
+    // Set the zoom level, moving the offset so whatever is under the pivot stays there.
+    // The ratio is taken after clamping, so zooming past a limit can't shift the view.
+    fn zoom_about(&mut self, zoom: f32, pivot: egui::Vec2) {
+        let old_zoom = self.zoom;
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        let zoom_change = self.zoom / old_zoom;
+        self.offset -= (pivot - self.offset) * (zoom_change - 1.0);
+    }
+    
+    fn is_zooming(&self) -> bool {
+        self.zoom_target.is_some() || self.fly_target.is_some()
+    }
+    
+    // Start an animated pan and zoom that centers a position at the given zoom
+    fn fly_to(&mut self, position: [f32; 3], zoom: f32) {
+        self.zoom_target = None;
+        self.fly_target = Some(FlyTarget {
+            position,
+            zoom: zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+        });
+    }
+    
+    // Ease the zoom and offset towards the fly target with the same smoothing as zooming
+    fn update_fly_animation(&mut self, dt: f32) {
+        let Some(target) = self.fly_target else {
+            return;
+        };
+        
+        let t = 1.0 - (-dt * ZOOM_SMOOTHING).exp();
+        self.zoom += (target.zoom - self.zoom) * t;
+        let (x, y) = self.project_target(target.position);
+        let centered = egui::vec2(-x * self.zoom, -y * self.zoom);
+        self.offset += (centered - self.offset) * t;
+        
+        if (self.zoom - target.zoom).abs() < target.zoom * 1e-3 && (centered - self.offset).length() < 0.5 {
+            self.zoom = target.zoom;
+            self.center_on(target.position);
+            self.fly_target = None;
+        }
+    }
+    
+    fn normalize(&self, position: [f32; 3]) -> [f32; 3] {
+        match &self.normalization {
+            Some(normalization) => normalization.apply(position),
+            None => position,
+        }
+    }
+    
+    // Project a world position onto the current projection's plane, ignoring transitions
+    fn project_target(&self, position: [f32; 3]) -> (f32, f32) {
+        self.projection.project(self.normalize(position), &self.orbit)
+    }
+    
+    // Perspective scale and depth of a position in the 3D view (1 and 0 in flat projections)
+    fn depth(&self, position: [f32; 3]) -> (f32, f32) {
+        if self.projection != Projection::Perspective3D {
+            return (1.0, 0.0);
+        }
+        let (_, _, depth, scale) = self.orbit.transform(self.normalize(position));
+        (scale, depth)
+    }
+    
+    // Project a world position to 2D, blending between projections during a transition
+    fn project(&self, position: [f32; 3]) -> (f32, f32) {
+        let position = self.normalize(position);
+        let to = self.projection.project(position, &self.orbit);
+        if !self.is_transitioning() {
+            return to;
+        }
+        
+        let from = self.from_projection.project(position, &self.orbit);
+        // Smoothstep easing
+        let t = self.projection_blend * self.projection_blend * (3.0 - 2.0 * self.projection_blend);
+        (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t)
+    }
+    
+    // Inverse of `project` for the flat projections: the world coordinates on the two
+    // visible axes, with the hidden axis left as None
+    fn unproject(&self, x: f32, y: f32) -> Option<[Option<f32>; 3]> {
+        if self.is_transitioning() {
+            return None;
+        }
+        let axes = self.projection.axes()?;
+        let mut world = [None; 3];
+        for (axis, value) in axes.into_iter().zip([x, y]) {
+            world[axis] = Some(match &self.normalization {
+                Some(normalization) => normalization.invert(axis, value),
+                None => value,
+            });
+        }
+        Some(world)
+    }
+    
+    // Zoom and pan so all given positions fit in a viewport of the given size
+    fn fit(&mut self, positions: impl IntoIterator<Item = [f32; 3]>, viewport: egui::Vec2) {
+        let mut min = egui::pos2(f32::INFINITY, f32::INFINITY);
+        let mut max = egui::pos2(f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for position in positions {
+            let (x, y) = self.project_target(position);
+            min = min.min(egui::pos2(x, y));
+            max = max.max(egui::pos2(x, y));
+        }
+        if min.x > max.x {
+            return;
+        }
+        
+        // Leave a small margin around the edges
+        let margin = 0.9;
+        let extent = (max - min).max(egui::vec2(1.0, 1.0));
+        self.zoom = (viewport.x * margin / extent.x)
+            .min(viewport.y * margin / extent.y)
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        self.zoom_target = None;
+        let center = min + (max - min) * 0.5;
+        self.offset = egui::vec2(-center.x * self.zoom, -center.y * self.zoom);
+    }
+    
+    // Pan so the given world position is in the center of the map
+    fn center_on(&mut self, position: [f32; 3]) {
+        let (x, y) = self.project_target(position);
+        self.offset = egui::vec2(-x * self.zoom, -y * self.zoom);
+    }
+}
+
+impl Default for StarMapApp {
+    fn default() -> Self {
+        Self {
+            star_map: None,
+            loading: false,
+            loading_started_ms: 0.0,
+            star_download: None,
+            star_systems_requested: false,
+            star_file_requested: false,
+            offline_star_data: false,
+            import_error: None,
+            bypass_star_cache: false,
+            error: None,
+            skipped_systems: 0,
+            view: MapView::default(),
+            saved_view_state: None,
+            view_saved_at: 0.0,
+            map_size: egui::vec2(800.0, 600.0),
+            map_rect: egui::Rect::NOTHING,
+            screenshot_pending: false,
+            selected_star: None,
+            tab_cycle: None,
+            pending_selection: None,
+            linked_selection: None,
+            persisted_selection: None,
+            selected_stars: HashSet::new(),
+            hovered_star: None,
+            route: None,
+            unreachable_route: None,
+            waypoints: Vec::new(),
+            route_copy_pending: None,
+            route_export_fallback: None,
+            dim_off_route_connections: true,
+            show_reach_hulls: false,
+            show_jump_range: false,
+            jump_range: 5,
+            jump_range_counts: HashMap::new(),
+            jump_range_source: None,
+            nearest_exchanges: Vec::new(),
+            nearest_exchanges_source: None,
+            reference_star: None,
+            measure_from: None,
+            reference_metric: DistanceMetric::Jumps,
+            reference_jumps: HashMap::new(),
+            reference_jumps_source: None,
+            following_ship: None,
+            range_ship: None,
+            focus_center: None,
+            focus_radius: 100.0,
+            search_query: String::new(),
+            last_search_query: String::new(),
+            previous_frame_query: String::new(),
+            goto_query: String::new(),
+            goto_error: None,
+            search_matches: Vec::new(),
+            sort_search_by_degree: false,
+            show_connections: true,
+            sparse_connections_while_dragging: true,
+            curved_connections: false,
+            directed_connections: false,
+            connection_width: DEFAULT_CONNECTION_WIDTH,
+            connection_opacity: DEFAULT_CONNECTION_OPACITY,
+            scale_connections_with_zoom: false,
+            color_by_sector: false,
+            show_sectors: false,
+            highlighted_star_types: HashSet::new(),
+            show_minimap: true,
+            show_hubs: false,
+            hub_scores: None,
+            visible_sectors: Vec::new(),
+            show_labels: false,
+            label_content: LabelContent::default(),
+            show_diagnostics: false,
+            highlight_suspicious: false,
+            animate_projection: true,
+            normalize_axes: false,
+            
+            auth_token: None,
+            auth_expiry_ms: None,
+            refreshing_auth: false,
+            next_auth_refresh_ms: 0.0,
+            username: String::new(),
+            password: String::new(),
+            login_error: None,
+            login_state: LoginState::Idle,
+            
+            user_data: None,
+            loading_user_data: false,
+            
+            ship_sort: ShipSort::Registration,
+            ship_min_age_days: 0,
+            ship_system_filter: None,
+            reveal_ship_list: false,
+            
+            cx_system_ids: HashSet::new(),
+            cx_names: HashMap::new(),
+            station_systems: HashMap::new(),
+            system_markers: HashMap::new(),
+            show_supply_lanes: false,
+            supply_lanes: Vec::new(),
+            markers_dirty: false,
+            marker_inputs: None,
+            
+            show_cx: true,
+            hide_cx_in_base_systems: false,
+            show_bases: true,
+            show_warehouses: true,
+            show_ships: true,
+            only_marked_systems: false,
+            only_marked_connections: true,
+            show_grid: false,
+            compact_markers: false,
+            click_nearest_star: false,
+            color_blind_markers: false,
+            show_inter_system_flights: true,
+            show_in_system_flights: true,
+            show_ship_trails: false,
+            show_arrival_labels: true,
+            
+            system_resources: HashMap::new(),
+            resource_tickers: Vec::new(),
+            resources_requested: false,
+            loading_resources: false,
+            resource_error: None,
+            resource_filter: None,
+            
+            exchange_prices: None,
+            exchange_prices_requested: false,
+            loading_exchange_prices: false,
+            exchange_prices_error: None,
+            price_filter: String::new(),
+            
+            system_planets: HashMap::new(),
+            planets_requested: HashSet::new(),
+            pending_planet_requests: Vec::new(),
+            planet_errors: HashMap::new(),
+            
+            api_base_url: String::new(),
+            
+            show_whats_new: false,
+            
+            production_windows_open: HashSet::new(),
+        }
+    }
+}
+
+impl StarMapApp {
+    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+        let settings = load_display_settings();
+        let view_state = load_view_state();
+        Self {
+            view: view_state.map(MapView::from_state).unwrap_or_default(),
+            saved_view_state: view_state,
+            show_whats_new: load_last_seen_version().as_deref() != Some(APP_VERSION),
+            api_base_url: load_api_base_url().unwrap_or_default(),
+            pending_selection: load_selected_star(),
+            label_content: settings.label_content,
+            compact_markers: settings.compact_markers,
+            click_nearest_star: settings.click_nearest_star,
+            color_blind_markers: settings.color_blind_markers,
+            show_connections: settings.show_connections,
+            show_labels: settings.show_labels,
+            show_cx: settings.show_cx,
+            show_bases: settings.show_bases,
+            show_warehouses: settings.show_warehouses,
+            show_ships: settings.show_ships,
+            only_marked_systems: settings.only_marked_systems,
+            only_marked_connections: settings.only_marked_connections,
+            show_grid: settings.show_grid,
+            // Rebuild markers so the restored toggles apply from the first frame
+            markers_dirty: true,
+            ..Self::default()
+        }
+    }
+    
+    // Replace the star map with freshly loaded systems
+    fn set_star_systems(&mut self, systems: Vec<data::StarSystem>, skipped: usize) {
+        if skipped > 0 {
+            tracing::warn!("Skipped {} malformed star systems", skipped);
+        }
+        self.skipped_systems = skipped;
+        let previous = self.star_map.replace(Arc::new(StarMap::from_systems(systems)));
+        if let Some(previous) = previous {
+            self.remap_node_state(&previous);
+        }
+        self.hub_scores = None;
+        // Re-run any search typed before the map finished loading
+        self.last_search_query.clear();
+        self.search_matches.clear();
+        self.markers_dirty = true;
+        self.restore_pending_selection();
+    }
+    
+    // Carry node indices from the previous map over to the new one by natural_id. Systems
+    // missing from the new map are dropped, and derived state is cleared to be rebuilt.
+    fn remap_node_state(&mut self, previous: &StarMap) {
+        let Some(star_map) = self.star_map.clone() else {
+            return;
+        };
+        let remap = |idx: NodeIndex| {
+            let natural_id = &previous.graph.node_weight(idx)?.natural_id;
+            star_map.natural_id_to_node.get(natural_id).copied()
+        };
+        
+        self.selected_star = self.selected_star.and_then(remap);
+        self.persisted_selection = self.persisted_selection.and_then(remap);
+        self.selected_stars = self.selected_stars.iter().filter_map(|&idx| remap(idx)).collect();
+        self.reference_star = self.reference_star.and_then(remap);
+        self.measure_from = self.measure_from.and_then(remap);
+        self.focus_center = self.focus_center.and_then(remap);
+        self.hovered_star = None;
+        self.tab_cycle = None;
+        self.reference_jumps.clear();
+        self.reference_jumps_source = None;
+        self.jump_range_counts.clear();
+        self.jump_range_source = None;
+        self.nearest_exchanges.clear();
+        self.nearest_exchanges_source = None;
+        self.supply_lanes.clear();
+        
+        // Routes are recomputed since connections may differ between the maps
+        self.waypoints = self.waypoints.iter().filter_map(|&idx| remap(idx)).collect();
+        let endpoints = self.route.as_ref()
+            .and_then(|route| Some((remap(*route.first()?)?, remap(*route.last()?)?)));
+        self.unreachable_route = self.unreachable_route
+            .and_then(|(from, to)| Some((remap(from)?, remap(to)?)));
+        if self.waypoints.len() >= 2 {
+            self.update_waypoint_route();
+        } else {
+            self.route = endpoints.and_then(|(from, to)| star_map.shortest_path(from, to));
+        }
+    }
+    
+    // Queue a planet fetch for a system unless it was already requested
+    fn request_system_planets(&mut self, system_id: &str) {
+        if self.planets_requested.insert(system_id.to_string()) {
+            self.pending_planet_requests.push(system_id.to_string());
+        }
+    }
+    
+    // Re-select the star from the URL or the previous session and center on it
+    fn restore_pending_selection(&mut self) {
+        let Some(star_map) = &self.star_map else {
+            return;
+        };
+        // A shared link takes precedence over the remembered selection. An unknown link
+        // leaves the remembered selection alone. Either is left unpersisted so the next
+        // frame writes it to storage and the URL.
+        if let Some(natural_id) = self.linked_selection.take() {
+            if let Some(&idx) = star_map.natural_id_to_node.get(&natural_id) {
+                self.pending_selection = None;
+                self.selected_star = Some(idx);
+                self.view.center_on(star_map.graph[idx].position);
+                return;
+            }
+            tracing::info!("Linked system {} not found in the star map", natural_id);
+        }
+        let Some(natural_id) = self.pending_selection.take() else {
+            return;
+        };
+        if let Some(&idx) = star_map.natural_id_to_node.get(&natural_id) {
+            self.selected_star = Some(idx);
+            self.view.center_on(star_map.graph[idx].position);
+        } else {
+            // The system no longer exists, so don't try to restore it again next time
+            tracing::info!("Stored selection {} not found in the star map", natural_id);
+            save_selected_star(None);
+            save_system_to_url(None);
+        }
+    }
+    
+    // Write the view to storage when it changes, at most once per interval so dragging
+    // and following a ship don't write every frame
+    fn persist_view_state(&mut self, ctx: &egui::Context) {
+        let state = self.view.state();
+        if self.saved_view_state == Some(state) {
+            return;
+        }
+        let now = ctx.input(|i| i.time);
+        if now - self.view_saved_at < VIEW_SAVE_INTERVAL_SECS {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(VIEW_SAVE_INTERVAL_SECS));
+            return;
+        }
+        save_view_state(&state);
+        self.saved_view_state = Some(state);
+        self.view_saved_at = now;
+    }
+    
+    // Save the map area of a requested screenshot once it arrives
+    fn handle_screenshot(&mut self, ctx: &egui::Context) {
+        if !self.screenshot_pending {
+            return;
+        }
+        let screenshot = ctx.input(|i| {
+            i.raw.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        let Some(screenshot) = screenshot else {
+            return;
+        };
+        self.screenshot_pending = false;
+        
+        let map_image = screenshot.region(&self.map_rect, Some(ctx.pixels_per_point()));
+        let filename = format!("prun_map_{}_{:.2}x.png", self.view.projection.file_name(), self.view.zoom);
+        if let Err(e) = export::download_png(&filename, &map_image) {
+            tracing::warn!("Failed to save map image: {:?}", e);
+        }
+    }
+    
+    // Write the selected star to storage and the URL whenever it changes
+    fn persist_selection(&mut self) {
+        if self.selected_star == self.persisted_selection || self.pending_selection.is_some() || self.linked_selection.is_some() {
+            return;
+        }
+        let Some(star_map) = &self.star_map else {
+            return;
+        };
+        self.persisted_selection = self.selected_star;
+        let natural_id = self.selected_star.map(|idx| star_map.graph[idx].natural_id.as_str());
+        save_selected_star(natural_id);
+        save_system_to_url(natural_id);
+    }
+    
+    fn api_config(&self) -> api::ApiConfig {
+        api::ApiConfig::new(&self.api_base_url)
+    }
+    
+    fn save_display_settings(&self) {
+        save_display_settings(&DisplaySettings {
+            label_content: self.label_content,
+            compact_markers: self.compact_markers,
+            click_nearest_star: self.click_nearest_star,
+            color_blind_markers: self.color_blind_markers,
+            show_connections: self.show_connections,
+            show_labels: self.show_labels,
+            show_cx: self.show_cx,
+            show_bases: self.show_bases,
+            show_warehouses: self.show_warehouses,
+            show_ships: self.show_ships,
+            only_marked_systems: self.only_marked_systems,
+            only_marked_connections: self.only_marked_connections,
+            show_grid: self.show_grid,
+        });
+    }
+
+    // Drop an expired session and prompt the user to log in again
+    fn expire_session(&mut self) {
+        self.auth_token = None;
+        self.auth_expiry_ms = None;
+        self.user_data = None;
+        self.password.clear();
+        self.login_error = Some("Session expired, please log in again".to_string());
+        clear_auth();
+        self.markers_dirty = true;
+    }
+
+    // Route every base system to its nearest commodity exchange
+    fn update_supply_lanes(&mut self) {
+        self.supply_lanes.clear();
+        let (Some(star_map), Some(user_data)) = (&self.star_map, &self.user_data) else {
+            return;
+        };
+        let to_nodes = |ids: &HashSet<String>| -> Vec<NodeIndex> {
+            let mut nodes: Vec<NodeIndex> = ids.iter()
+                .filter_map(|id| star_map.natural_id_to_node.get(id).copied())
+                .collect();
+            nodes.sort();
+            nodes
+        };
+        self.supply_lanes = star_map.paths_to_nearest(&to_nodes(&user_data.base_system_ids), &to_nodes(&self.cx_system_ids));
+    }
+    
+    fn marker_inputs(&self) -> MarkerInputs {
+        let user_data = self.user_data.as_ref();
+        MarkerInputs {
+            star_map: self.star_map.clone(),
+            toggles: [
+                self.show_cx,
+                self.show_bases,
+                self.show_warehouses,
+                self.show_ships,
+                self.show_in_system_flights,
+                self.hide_cx_in_base_systems,
+            ],
+            cx_system_ids: self.cx_system_ids.clone(),
+            station_systems: self.station_systems.clone(),
+            base_system_ids: user_data.map(|user_data| user_data.base_system_ids.clone()).unwrap_or_default(),
+            ship_system_ids: user_data.map(|user_data| user_data.ship_system_ids.clone()).unwrap_or_default(),
+            warehouse_locations: user_data.map(|user_data| user_data.warehouse_locations.clone()).unwrap_or_default(),
+            in_system_flight_origins: user_data
+                .map(|user_data| {
+                    user_data.flight_paths.iter()
+                        .filter(|flight| flight.is_in_system)
+                        .map(|flight| flight.origin_system_id.clone())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+    
+    // Rebuild the markers if anything they're built from changed since the last build
+    fn refresh_system_markers(&mut self) {
+        let inputs = self.marker_inputs();
+        if self.marker_inputs.as_ref() == Some(&inputs) {
+            return;
+        }
+        self.update_system_markers();
+        self.marker_inputs = Some(inputs);
+    }
+    
+    fn update_system_markers(&mut self) {
+        self.system_markers.clear();
+        self.update_supply_lanes();
+        // Bases and exchanges may have changed, so rank them again
+        self.nearest_exchanges_source = None;
+        
+        // Resolve warehouse locations (planets or CX stations) to their systems
+        let warehouse_system_ids: HashSet<String> = self.user_data.as_ref()
+            .map(|user_data| {
+                user_data.warehouse_locations.iter()
+                    .map(|location| {
+                        self.station_systems.get(location).cloned()
+                            .unwrap_or_else(|| extract_system_from_planet(location))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        
+        // Collect all system IDs that have any marker
+        let mut all_system_ids: HashSet<String> = HashSet::new();
+        
+        if self.show_cx {
+            all_system_ids.extend(self.cx_system_ids.iter().cloned());
+        }
+        
+        if let Some(user_data) = &self.user_data {
+            if self.show_bases {
+                all_system_ids.extend(user_data.base_system_ids.iter().cloned());
+            }
+            if self.show_warehouses {
+                all_system_ids.extend(warehouse_system_ids.iter().cloned());
+            }
             if self.show_ships {
                 all_system_ids.extend(user_data.ship_system_ids.iter().cloned());
                 // Also add in-system flights as ship markers
-                for flight in &user_data.flight_paths {
-                    if flight.is_in_system {
-                        all_system_ids.insert(flight.origin_system_id.clone());
+                if self.show_in_system_flights {
+                    for flight in &user_data.flight_paths {
+                        if flight.is_in_system {
+                            all_system_ids.insert(flight.origin_system_id.clone());
+                        }
                     }
                 }
             }
         }
         
         // For each system, collect all applicable markers in priority order (outer to inner)
-        // CX (red) -> Base (green) -> Ship (blue)
+        // CX (red) -> Base (green) -> Warehouse (orange) -> Ship (blue)
         for system_id in all_system_ids {
             let mut markers = Vec::new();
             
-            if self.show_cx && self.cx_system_ids.contains(&system_id) {
+            let in_base_system = self.user_data.as_ref()
+                .is_some_and(|user_data| user_data.base_system_ids.contains(&system_id));
+            if self.show_cx
+                && self.cx_system_ids.contains(&system_id)
+                && !(self.hide_cx_in_base_systems && in_base_system)
+            {
                 markers.push(SystemMarker::CommodityExchange);
             }
             
@@ -175,12 +1447,16 @@ impl StarMapApp {
                 if self.show_bases && user_data.base_system_ids.contains(&system_id) {
                     markers.push(SystemMarker::Base);
                 }
+                if self.show_warehouses && warehouse_system_ids.contains(&system_id) {
+                    markers.push(SystemMarker::Warehouse);
+                }
                 if self.show_ships {
                     // Check for docked ships
                     let has_docked_ship = user_data.ship_system_ids.contains(&system_id);
                     // Check for in-system flights
-                    let has_in_system_flight = user_data.flight_paths.iter()
-                        .any(|f| f.is_in_system && f.origin_system_id == system_id);
+                    let has_in_system_flight = self.show_in_system_flights
+                        && user_data.flight_paths.iter()
+                            .any(|f| f.is_in_system && f.origin_system_id == system_id);
                     
                     if has_docked_ship || has_in_system_flight {
                         markers.push(SystemMarker::Ship);
@@ -194,12 +1470,208 @@ impl StarMapApp {
         }
     }
 
-    fn world_to_screen(&self, node: &StarNode, rect: egui::Rect) -> egui::Pos2 {
-        let (x, y) = match self.view.projection {
-            Projection::XY => (node.position[0], node.position[1]),
-            Projection::XZ => (node.position[0], node.position[2]),
-            Projection::YZ => (node.position[1], node.position[2]),
+    // Move the selection to a neighbor of the selected star and center on it
+    fn select_neighbor(&mut self, target: NeighborTarget) {
+        let (Some(star_map), Some(selected)) = (&self.star_map, self.selected_star) else {
+            return;
+        };
+        let neighbors: Vec<NodeIndex> = star_map.graph.neighbors(selected).collect();
+        if neighbors.is_empty() {
+            return;
+        }
+        
+        let next = match target {
+            // Keep cycling around the same hub while Tab is pressed repeatedly
+            NeighborTarget::Next => {
+                let (hub, index) = match self.tab_cycle {
+                    Some((hub, index)) if star_map.graph.neighbors(hub).nth(index) == Some(selected) => {
+                        (hub, (index + 1) % star_map.degree(hub))
+                    }
+                    _ => (selected, 0),
+                };
+                self.tab_cycle = Some((hub, index));
+                star_map.graph.neighbors(hub).nth(index)
+            }
+            // Pick the neighbor best aligned with the direction on screen, ignoring anything behind
+            NeighborTarget::Direction(dir) => {
+                let from = self.world_to_screen(&star_map.graph[selected], self.map_rect);
+                let best = neighbors.into_iter()
+                    .filter_map(|idx| {
+                        let offset = self.world_to_screen(&star_map.graph[idx], self.map_rect) - from;
+                        let alignment = offset.normalized().dot(dir);
+                        (alignment > 0.0).then_some((idx, alignment))
+                    })
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                self.tab_cycle = None;
+                best.map(|(idx, _)| idx)
+            }
+        };
+        
+        if let Some(next) = next {
+            self.selected_star = Some(next);
+            self.view.center_on(star_map.graph[next].position);
+            self.following_ship = None;
+        }
+    }
+    
+    fn screen_to_world(&self, screen: egui::Pos2, rect: egui::Rect) -> Option<[Option<f32>; 3]> {
+        let projected = (screen - rect.center() - self.view.offset) / self.view.zoom;
+        self.view.unproject(projected.x, projected.y)
+    }
+    
+    // Scale bar and cursor coordinates in the bottom-left corner of the map
+    fn draw_scale_bar(&self, painter: &egui::Painter, rect: egui::Rect, hover_pos: Option<egui::Pos2>) {
+        if self.view.projection.axes().is_none() {
+            return;
+        }
+        let color = egui::Color32::from_gray(200);
+        let font = egui::FontId::proportional(11.0);
+        let origin = rect.left_bottom() + egui::vec2(SCALE_BAR_MARGIN, -SCALE_BAR_MARGIN);
+        
+        let (length, exponent) = round_to_nice_step(SCALE_BAR_TARGET_PX / self.view.zoom, false);
+        let bar_px = length * self.view.zoom;
+        
+        let stroke = egui::Stroke::new(1.5, color);
+        let end = origin + egui::vec2(bar_px, 0.0);
+        painter.line_segment([origin, end], stroke);
+        painter.line_segment([origin, origin - egui::vec2(0.0, 4.0)], stroke);
+        painter.line_segment([end, end - egui::vec2(0.0, 4.0)], stroke);
+        let unit = if self.normalize_axes { "units (normalized)" } else { "units" };
+        painter.text(
+            origin - egui::vec2(0.0, 6.0),
+            egui::Align2::LEFT_BOTTOM,
+            format!("{:.*} {}", (-exponent).max(0) as usize, length, unit),
+            font.clone(),
+            color,
+        );
+        
+        let world = hover_pos
+            .filter(|pos| rect.contains(*pos))
+            .and_then(|pos| self.screen_to_world(pos, rect));
+        if let Some(world) = world {
+            let readout: Vec<String> = ["X", "Y", "Z"].iter().zip(world)
+                .filter_map(|(name, value)| Some(format!("{}: {:.1}", name, value?)))
+                .collect();
+            painter.text(
+                origin + egui::vec2(bar_px + 12.0, 0.0),
+                egui::Align2::LEFT_CENTER,
+                readout.join("  "),
+                font,
+                color,
+            );
+        }
+    }
+    
+    // Faint grid aligned to world coordinates with the zero lines and axis names emphasized.
+    // Only drawn in the flat projections, where screen position maps linearly to world position.
+    fn draw_grid(&self, painter: &egui::Painter, rect: egui::Rect) {
+        let Some(axes) = self.view.projection.axes() else {
+            return;
+        };
+        let (Some(top_left), Some(bottom_right)) = (
+            self.screen_to_world(rect.left_top(), rect),
+            self.screen_to_world(rect.right_bottom(), rect),
+        ) else {
+            return;
         };
+        let line_color = egui::Color32::from_gray(35);
+        let axis_color = egui::Color32::from_gray(70);
+        let font = egui::FontId::proportional(11.0);
+        let names = ["X", "Y", "Z"];
+        
+        // Screen axis 0 is horizontal, 1 is vertical
+        for (screen_axis, &axis) in axes.iter().enumerate() {
+            let (Some(start), Some(end)) = (top_left[axis], bottom_right[axis]) else {
+                continue;
+            };
+            let (screen_start, screen_end) = if screen_axis == 0 {
+                (rect.left(), rect.right())
+            } else {
+                (rect.top(), rect.bottom())
+            };
+            if (end - start).abs() < f32::EPSILON {
+                continue;
+            }
+            let px_per_unit = (screen_end - screen_start) / (end - start);
+            let to_screen = |value: f32| screen_start + (value - start) * px_per_unit;
+            let (step, _) = round_to_nice_step(GRID_MIN_SPACING_PX / px_per_unit.abs(), true);
+            
+            let (low, high) = (start.min(end), start.max(end));
+            let first = (low / step).ceil() as i64;
+            let last = ((high / step).floor() as i64).min(first.saturating_add(GRID_MAX_LINES));
+            for i in first..=last {
+                let value = i as f32 * step;
+                let at = to_screen(value);
+                let is_zero = value.abs() < step * 0.5;
+                let stroke = egui::Stroke::new(1.0, if is_zero { axis_color } else { line_color });
+                let points = if screen_axis == 0 {
+                    [egui::pos2(at, rect.top()), egui::pos2(at, rect.bottom())]
+                } else {
+                    [egui::pos2(rect.left(), at), egui::pos2(rect.right(), at)]
+                };
+                painter.line_segment(points, stroke);
+            }
+            
+            // Name each axis at the far end of its zero line, or along the edge if that's off screen.
+            // Vertical grid lines mark positions along the horizontal axis, and vice versa.
+            let other = axes[1 - screen_axis];
+            let (Some(other_start), Some(other_end)) = (top_left[other], bottom_right[other]) else {
+                continue;
+            };
+            if (other_end - other_start).abs() < f32::EPSILON {
+                continue;
+            }
+            let label_pos = if screen_axis == 0 {
+                let zero_y = rect.top() + (0.0 - other_start) / (other_end - other_start) * rect.height();
+                egui::pos2(rect.right() - 8.0, zero_y.clamp(rect.top() + 8.0, rect.bottom() - 8.0))
+            } else {
+                let zero_x = rect.left() + (0.0 - other_start) / (other_end - other_start) * rect.width();
+                egui::pos2(zero_x.clamp(rect.left() + 8.0, rect.right() - 8.0), rect.top() + 8.0)
+            };
+            painter.text(
+                label_pos,
+                if screen_axis == 0 { egui::Align2::RIGHT_BOTTOM } else { egui::Align2::LEFT_TOP },
+                names[axis],
+                font.clone(),
+                axis_color,
+            );
+        }
+    }
+    
+    fn marker_label(&self, marker: SystemMarker, system_id: &str) -> String {
+        let emoji = marker.emoji(self.color_blind_markers);
+        match marker {
+            SystemMarker::CommodityExchange => {
+                if let Some(cx_name) = self.cx_names.get(system_id) {
+                    format!("{} CX: {}", emoji, cx_name)
+                } else {
+                    format!("{} Commodity Exchange", emoji)
+                }
+            }
+            SystemMarker::Base => format!("{} Your Base", emoji),
+            SystemMarker::Warehouse => format!("{} Your Warehouse", emoji),
+            SystemMarker::Ship => format!("{} Your Ship", emoji),
+        }
+    }
+    
+    fn connection_color(&self) -> egui::Color32 {
+        egui::Color32::from_rgb(100, 100, 150).gamma_multiply(self.connection_opacity)
+    }
+    
+    fn connection_stroke_width(&self) -> f32 {
+        if self.scale_connections_with_zoom {
+            self.connection_width * self.view.zoom
+        } else {
+            self.connection_width
+        }
+    }
+    
+    fn world_to_screen(&self, node: &StarNode, rect: egui::Rect) -> egui::Pos2 {
+        self.position_to_screen(node.position, rect)
+    }
+
+    fn position_to_screen(&self, position: [f32; 3], rect: egui::Rect) -> egui::Pos2 {
+        let (x, y) = self.view.project(position);
 
         let center = rect.center();
         egui::Pos2::new(
@@ -208,63 +1680,386 @@ impl StarMapApp {
         )
     }
 
-    fn draw_map(&mut self, ui: &mut egui::Ui) {
-        let (response, painter) = ui.allocate_painter(
-            ui.available_size(),
-            egui::Sense::click_and_drag(),
-        );
-
-        let rect = response.rect;
+    // Closest visible star to a screen point, within `max_distance` pixels
+    fn nearest_star_on_screen(&self, star_map: &StarMap, point: egui::Pos2, rect: egui::Rect, max_distance: f32) -> Option<NodeIndex> {
+        star_map.graph.node_indices()
+            .map(|idx| (idx, self.world_to_screen(&star_map.graph[idx], rect)))
+            .filter(|(_, pos)| rect.contains(*pos))
+            .map(|(idx, pos)| (idx, (pos - point).length()))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(idx, _)| idx)
+    }
 
-        // Handle panning
-        if response.dragged() {
-            self.view.offset += response.drag_delta();
-        }
+    // Current interpolated position of the ship being followed, if it is still in flight
+    fn followed_ship_position(&self) -> Option<[f32; 3]> {
+        let ship_id = self.following_ship.as_ref()?;
+        let star_map = self.star_map.as_ref()?;
+        let flight = self.user_data.as_ref()?.flight_paths.iter()
+            .find(|f| f.ship_id.as_ref() == Some(ship_id))?;
+        star_map.flight_position(flight, now_ms())
+    }
 
-        // Handle zooming
-        if let Some(hover_pos) = response.hover_pos() {
-            let scroll = ui.input(|i| i.raw_scroll_delta.y);
-            if scroll != 0.0 {
-                let zoom_factor = 1.0 + scroll * 0.001;
-                let old_zoom = self.view.zoom;
-                self.view.zoom = (self.view.zoom * zoom_factor).clamp(0.05, 5.0);
+    // Position of the range ship's system and its estimated FTL range, if both are known
+    fn ftl_range_circle(&self, star_map: &StarMap) -> Option<([f32; 3], f32)> {
+        let ship_id = self.range_ship.as_ref()?;
+        let ship = self.user_data.as_ref()?.ships.iter().find(|ship| &ship.ship_id == ship_id)?;
+        let radius = ship.ftl_range_estimate()?;
+        let system_id = extract_system_from_planet(ship.location.as_deref().filter(|location| !location.is_empty())?);
+        let &idx = star_map.natural_id_to_node.get(&system_id)?;
+        Some((star_map.graph[idx].position, radius))
+    }
 
-                // Zoom towards cursor
-                let zoom_change = self.view.zoom / old_zoom;
-                let cursor_offset = hover_pos - rect.center() - self.view.offset;
-                self.view.offset -= cursor_offset * (zoom_change - 1.0);
-            }
+    // Recompute jump counts from the reference system when it changes
+    fn update_reference_jumps(&mut self) {
+        if self.reference_jumps_source == self.reference_star {
+            return;
         }
-
+        self.reference_jumps_source = self.reference_star;
+        self.reference_jumps = match (&self.star_map, self.reference_star) {
+            (Some(star_map), Some(reference)) => star_map.reachable_within(reference, usize::MAX),
+            _ => HashMap::new(),
+        };
+    }
+
+    // Recompute jump counts around the selection when it or the range changes
+    fn update_jump_range_counts(&mut self) {
+        let source = self.selected_star
+            .filter(|_| self.show_jump_range)
+            .map(|selected| (selected, self.jump_range));
+        if self.jump_range_source == source {
+            return;
+        }
+        self.jump_range_source = source;
+        self.jump_range_counts = match (&self.star_map, source) {
+            (Some(star_map), Some((selected, range))) => star_map.reachable_within(selected, range),
+            _ => HashMap::new(),
+        };
+    }
+
+    // Rank the exchanges reachable from the selected base system when the selection changes
+    fn update_nearest_exchanges(&mut self) {
+        if self.nearest_exchanges_source == self.selected_star {
+            return;
+        }
+        self.nearest_exchanges_source = self.selected_star;
+        self.nearest_exchanges.clear();
+        let (Some(star_map), Some(user_data), Some(selected)) = (&self.star_map, &self.user_data, self.selected_star) else {
+            return;
+        };
+        if !user_data.base_system_ids.contains(&star_map.graph[selected].natural_id) || self.cx_system_ids.is_empty() {
+            return;
+        }
+        // Layers come out of the BFS in jump order, so no sorting is needed
+        self.nearest_exchanges = star_map.jump_layers(selected, usize::MAX)
+            .iter()
+            .enumerate()
+            .flat_map(|(jumps, layer)| layer.iter().map(move |&idx| (jumps, idx)))
+            .filter(|(_, idx)| self.cx_system_ids.contains(&star_map.graph[*idx].natural_id))
+            .collect();
+    }
+
+    fn draw_map(&mut self, ui: &mut egui::Ui) {
+        let (response, painter) = ui.allocate_painter(
+            ui.available_size(),
+            egui::Sense::click_and_drag(),
+        );
+
+        let rect = response.rect;
+        self.map_size = rect.size();
+        self.map_rect = rect;
+        self.update_reference_jumps();
+
+        let now = ui.input(|i| i.time);
+        self.view.update_projection_transition(now, self.animate_projection);
+        self.view.update_zoom_animation(ui.input(|i| i.stable_dt));
+        self.view.update_fly_animation(ui.input(|i| i.stable_dt));
+        self.view.normalization = match &self.star_map {
+            Some(star_map) if self.normalize_axes => Some(AxisNormalization::from_bounds(&star_map.stats.bounds)),
+            _ => None,
+        };
+        if let Some(star_map) = &self.star_map {
+            // Orbit the galaxy center from far enough away that the whole galaxy is in front of the camera
+            let bounds = &star_map.stats.bounds;
+            let size = bounds.size();
+            self.view.orbit.center = self.view.normalize(std::array::from_fn(|axis| (bounds.min[axis] + bounds.max[axis]) * 0.5));
+            self.view.orbit.distance = size[0].max(size[1]).max(size[2]).max(1.0) * 2.0;
+        }
+
+        // Dragging rotates the 3D view and pans the flat ones (manually panning stops following a ship)
+        if response.dragged() {
+            self.view.fly_target = None;
+            if self.view.projection == Projection::Perspective3D {
+                self.view.orbit.rotate(response.drag_delta());
+            } else {
+                self.view.offset += response.drag_delta();
+                self.following_ship = None;
+            }
+        }
+        
+        // Keep the followed ship centered
+        let followed_position = self.followed_ship_position();
+        match followed_position {
+            Some(position) => self.view.center_on(position),
+            None => self.following_ship = None,
+        }
+
+        // Handle zooming (towards the cursor)
+        if let Some(hover_pos) = response.hover_pos() {
+            let scroll = ui.input(|i| i.raw_scroll_delta.y);
+            if scroll != 0.0 {
+                // Exponential so large high-resolution wheel deltas can't drive the factor to zero or below
+                self.view.zoom_by((scroll * 0.001).exp(), hover_pos - rect.center());
+            }
+        }
+
         // Draw background
         painter.rect_filled(rect, 0.0, egui::Color32::from_rgb(10, 10, 20));
+        if self.show_grid {
+            self.draw_grid(&painter, rect);
+        }
 
         if let Some(star_map) = &self.star_map {
             let star_map = Arc::clone(star_map);
             
+            // Project every star once per frame; all passes below index into this
+            let screen_positions: Vec<egui::Pos2> = star_map.graph.node_weights()
+                .map(|node| self.world_to_screen(node, rect))
+                .collect();
+            let screen_pos = |idx: NodeIndex| screen_positions[idx.index()];
+            // Perspective scale and depth of every star, for sizing and draw order in 3D
+            let depths: Vec<(f32, f32)> = star_map.graph.node_weights()
+                .map(|node| self.view.depth(node.position))
+                .collect();
+            
+            // Everything outside the focus sphere (if one is set) or without the filtered
+            // resource (if one is chosen) is dimmed
+            let focus_node = self.focus_center.map(|idx| &star_map.graph[idx]);
+            let focus_radius = self.focus_radius;
+            let resource_filter = self.resource_filter.as_ref();
+            let system_resources = &self.system_resources;
+            let in_focus = |node: &StarNode| {
+                focus_node.is_none_or(|center| center.distance_to(node) <= focus_radius)
+                    && resource_filter.is_none_or(|ticker| {
+                        system_resources.get(&node.natural_id).is_some_and(|r| r.contains_key(ticker))
+                    })
+            };
+            
+            // Connections along the active route, stored with the lower index first
+            let route_edges: HashSet<(NodeIndex, NodeIndex)> = self.route.iter()
+                .flat_map(|route| route.windows(2))
+                .map(|pair| (pair[0].min(pair[1]), pair[0].max(pair[1])))
+                .collect();
+            let dim_off_route = self.dim_off_route_connections && !route_edges.is_empty();
+            
+            // The most central junctions, when highlighted
+            if self.show_hubs && self.hub_scores.is_none() {
+                self.hub_scores = Some(star_map.hub_scores(HUB_BETWEENNESS_SAMPLES));
+            }
+            let hubs: HashSet<NodeIndex> = match &self.hub_scores {
+                Some(scores) if self.show_hubs => scores.iter().take(HUB_COUNT).map(|score| score.node).collect(),
+                _ => HashSet::new(),
+            };
+            
+            // Stars left on the map when only marked systems are shown (the selection stays visible)
+            let marked: Option<HashSet<NodeIndex>> = self.only_marked_systems.then(|| {
+                self.system_markers.keys()
+                    .filter_map(|id| star_map.natural_id_to_node.get(id).copied())
+                    .chain(self.selected_star)
+                    .collect()
+            });
+            let is_shown = |idx: NodeIndex| marked.as_ref().is_none_or(|marked| marked.contains(&idx));
+            let edge_shown = |a: NodeIndex, b: NodeIndex| !self.only_marked_connections || (is_shown(a) && is_shown(b));
+            
+            // Jump counts from the selected star, when the range overlay is on
+            let jump_range = self.jump_range_source.is_some().then_some(&self.jump_range_counts);
+            
+            // Sector regions go behind everything else. Hulls are taken over the projected
+            // positions, so they follow projection changes.
+            if self.show_sectors || self.color_by_sector {
+                let mut sector_points: HashMap<&str, Vec<egui::Pos2>> = HashMap::new();
+                for (node, &pos) in star_map.graph.node_weights().zip(&screen_positions) {
+                    sector_points.entry(node.sector_id.as_str()).or_default().push(pos);
+                }
+                for (sector_id, points) in sector_points {
+                    let hull = convex_hull(&points);
+                    if hull.len() < 3 {
+                        continue;
+                    }
+                    let color = sector_color(sector_id);
+                    let outline = egui::Stroke::new(1.0, color.gamma_multiply(SECTOR_OUTLINE_ALPHA));
+                    if !self.show_sectors {
+                        painter.add(egui::Shape::closed_line(hull, outline));
+                        continue;
+                    }
+                    
+                    let centroid = hull.iter().fold(egui::Vec2::ZERO, |sum, p| sum + p.to_vec2()) / hull.len() as f32;
+                    painter.add(egui::Shape::convex_polygon(hull, color.gamma_multiply(SECTOR_FILL_ALPHA), outline));
+                    if rect.contains(centroid.to_pos2()) {
+                        painter.text(
+                            centroid.to_pos2(),
+                            egui::Align2::CENTER_CENTER,
+                            sector_id,
+                            egui::FontId::proportional(11.0),
+                            color.gamma_multiply(0.6),
+                        );
+                    }
+                }
+            }
+            
             // Draw connections first (behind stars)
-            if self.show_connections {
-                for edge in star_map.graph.edge_indices() {
-                    if let Some((a, b)) = star_map.graph.edge_endpoints(edge) {
+            if self.show_connections && self.directed_connections {
+                let one_way_color = egui::Color32::from_rgb(255, 140, 60);
+                for edge in star_map.directed.edge_indices() {
+                    let Some((a, b)) = star_map.directed.edge_endpoints(edge) else {
+                        continue;
+                    };
+                    if !edge_shown(a, b) {
+                        continue;
+                    }
+                    let pos_a = screen_pos(a);
+                    let pos_b = screen_pos(b);
+                    if !rect.contains(pos_a) && !rect.contains(pos_b) {
+                        continue;
+                    }
+                    
+                    let color = if star_map.is_one_way(a, b) {
+                        one_way_color
+                    } else {
+                        self.connection_color()
+                    };
+                    let stroke = egui::Stroke::new(self.connection_stroke_width(), color);
+                    painter.line_segment([pos_a, pos_b], stroke);
+                    
+                    // Arrowhead short of the destination star. Links listed both ways get two opposing heads.
+                    let dir = (pos_b - pos_a).normalized();
+                    let tip = pos_b - dir * (4.0 + self.view.zoom * 2.0);
+                    let perp = dir.rot90();
+                    painter.add(egui::Shape::convex_polygon(
+                        vec![tip, tip - dir * 5.0 + perp * 2.5, tip - dir * 5.0 - perp * 2.5],
+                        color,
+                        egui::Stroke::NONE,
+                    ));
+                }
+            } else if self.show_connections {
+                // Thin out the mesh while panning to keep dragging smooth on large maps
+                let sparse = self.sparse_connections_while_dragging && response.dragged();
+                for (i, edge) in star_map.graph.edge_indices().enumerate() {
+                    if sparse && i % DRAG_CONNECTION_STRIDE != 0 {
+                        continue;
+                    }
+
+                    if let Some((a, b)) = star_map.graph.edge_endpoints(edge).filter(|&(a, b)| edge_shown(a, b)) {
                         let node_a = &star_map.graph[a];
                         let node_b = &star_map.graph[b];
-                        let pos_a = self.world_to_screen(node_a, rect);
-                        let pos_b = self.world_to_screen(node_b, rect);
+                        let pos_a = screen_pos(a);
+                        let pos_b = screen_pos(b);
 
                         // Only draw if at least one endpoint is visible
                         if rect.contains(pos_a) || rect.contains(pos_b) {
-                            painter.line_segment(
-                                [pos_a, pos_b],
-                                egui::Stroke::new(0.5, egui::Color32::from_rgba_unmultiplied(100, 100, 150, 80)),
-                            );
+                            let mut color = self.connection_color();
+                            if !in_focus(node_a) && !in_focus(node_b) {
+                                color = color.gamma_multiply(FOCUS_DIM_FACTOR);
+                            }
+                            if dim_off_route && !route_edges.contains(&(a.min(b), a.max(b))) {
+                                color = color.gamma_multiply(ROUTE_DIM_FACTOR);
+                            }
+                            let stroke = egui::Stroke::new(self.connection_stroke_width(), color);
+                            if self.curved_connections {
+                                // Bow the line sideways so connections at similar angles separate
+                                let delta = pos_b - pos_a;
+                                let control = pos_a + delta * 0.5 + delta.rot90() * CONNECTION_CURVE_BOW;
+                                painter.add(egui::epaint::QuadraticBezierShape::from_points_stroke(
+                                    [pos_a, control, pos_b],
+                                    false,
+                                    egui::Color32::TRANSPARENT,
+                                    stroke,
+                                ));
+                            } else {
+                                painter.line_segment([pos_a, pos_b], stroke);
+                            }
                         }
                     }
                 }
             }
             
+            // Highlight the connection nearest the cursor when no star is hovered
+            let edge_hover_pos = response.hover_pos()
+                .filter(|_| self.show_connections && self.hovered_star.is_none() && !response.dragged());
+            let hovered_edge = edge_hover_pos.and_then(|hover_pos| {
+                star_map.graph.edge_indices()
+                    .filter_map(|edge| star_map.graph.edge_endpoints(edge))
+                    .filter(|&(a, b)| edge_shown(a, b))
+                    .map(|(a, b)| {
+                        let pos_a = screen_pos(a);
+                        let pos_b = screen_pos(b);
+                        (a, b, pos_a, pos_b, distance_to_segment(hover_pos, pos_a, pos_b))
+                    })
+                    .filter(|edge| edge.4 <= EDGE_HOVER_RADIUS)
+                    .min_by(|x, y| x.4.total_cmp(&y.4))
+            });
+            if let Some((a, b, pos_a, pos_b, _)) = hovered_edge {
+                painter.line_segment([pos_a, pos_b], egui::Stroke::new(2.5, egui::Color32::from_rgb(180, 200, 255)));
+                let (node_a, node_b) = (&star_map.graph[a], &star_map.graph[b]);
+                response.clone().on_hover_ui_at_pointer(|ui| {
+                    ui.strong(format!("{} ↔ {}", node_a.name, node_b.name));
+                    ui.label(format!("{} ↔ {} · {:.1} units", node_a.natural_id, node_b.natural_id, node_a.distance_to(node_b)));
+                });
+            }
+            
+            // Outline the systems reachable within 1, 2, ... jumps of the selected star
+            if let (true, Some(selected)) = (self.show_reach_hulls, self.selected_star) {
+                let mut reachable = Vec::new();
+                for (depth, layer) in star_map.jump_layers(selected, REACH_HULL_DEPTH).iter().enumerate() {
+                    reachable.extend(layer.iter().map(|&idx| screen_pos(idx)));
+                    if depth == 0 {
+                        continue;
+                    }
+                    let hull = convex_hull(&reachable);
+                    if hull.len() < 3 {
+                        continue;
+                    }
+                    let fade = 1.0 - (depth - 1) as f32 / REACH_HULL_DEPTH as f32;
+                    let color = egui::Color32::from_rgb(120, 220, 200).gamma_multiply(fade);
+                    painter.add(egui::Shape::closed_line(hull, egui::Stroke::new(1.5, color)));
+                }
+            }
+            
+            // Estimated FTL range around the chosen ship's current system
+            if let Some((center, radius)) = self.ftl_range_circle(star_map) {
+                let screen_center = self.position_to_screen(center, rect);
+                let edge = self.position_to_screen([center[0] + radius, center[1], center[2]], rect);
+                let color = egui::Color32::from_rgb(90, 160, 255);
+                painter.circle(
+                    screen_center,
+                    screen_center.distance(edge),
+                    color.gamma_multiply(0.08),
+                    egui::Stroke::new(1.0, color.gamma_multiply(0.6)),
+                );
+            }
+            
+            // Supply lanes from each base to its nearest exchange
+            if self.show_supply_lanes {
+                let lane_color = egui::Color32::from_rgb(120, 230, 140).gamma_multiply(0.8);
+                for lane in &self.supply_lanes {
+                    let points: Vec<egui::Pos2> = lane.iter()
+                        .map(|&idx| screen_pos(idx))
+                        .collect();
+                    painter.add(egui::Shape::dashed_line(&points, egui::Stroke::new(2.0, lane_color), 6.0, 3.0));
+                }
+            }
+            
+            // Draw the active route on top of the connection mesh
+            if let Some(route) = &self.route {
+                let points: Vec<egui::Pos2> = route.iter()
+                    .map(|&idx| screen_pos(idx))
+                    .collect();
+                painter.add(egui::Shape::line(points, egui::Stroke::new(2.5, egui::Color32::from_rgb(255, 220, 80))));
+            }
+            
             // Draw flight paths (blue lines with arrows for inter-system, rings handled with markers)
             let flight_color = egui::Color32::from_rgb(80, 160, 255);
-            if self.show_ships {
+            if self.show_ships && self.show_inter_system_flights {
                 if let Some(user_data) = &self.user_data {
                     for flight in &user_data.flight_paths {
                         if !flight.is_in_system {
@@ -273,10 +2068,8 @@ impl StarMapApp {
                                 star_map.natural_id_to_node.get(&flight.origin_system_id),
                                 star_map.natural_id_to_node.get(&flight.destination_system_id),
                             ) {
-                                let origin_node = &star_map.graph[*origin_idx];
-                                let dest_node = &star_map.graph[*dest_idx];
-                                let pos_origin = self.world_to_screen(origin_node, rect);
-                                let pos_dest = self.world_to_screen(dest_node, rect);
+                                let pos_origin = screen_pos(*origin_idx);
+                                let pos_dest = screen_pos(*dest_idx);
                                 
                                 // Only draw if at least one endpoint is visible
                                 if rect.contains(pos_origin) || rect.contains(pos_dest) {
@@ -286,21 +2079,57 @@ impl StarMapApp {
                                         egui::Stroke::new(2.0, flight_color),
                                     );
                                     
-                                    // Draw arrow at midpoint pointing towards destination
-                                    let mid = pos_origin + (pos_dest - pos_origin) * 0.6;
                                     let dir = (pos_dest - pos_origin).normalized();
-                                    let arrow_size = 8.0;
-                                    let perp = egui::vec2(-dir.y, dir.x);
+                                    let progress = flight.progress(now_ms());
                                     
-                                    let arrow_tip = mid + dir * arrow_size;
-                                    let arrow_left = mid - dir * arrow_size * 0.5 + perp * arrow_size * 0.5;
-                                    let arrow_right = mid - dir * arrow_size * 0.5 - perp * arrow_size * 0.5;
+                                    if let Some(t) = progress {
+                                        // Draw the ship where it currently is along the line
+                                        let ship_pos = pos_origin + (pos_dest - pos_origin) * t;
+                                        painter.circle_filled(ship_pos, 3.5, flight_color);
+                                        painter.circle_stroke(ship_pos, 3.5, egui::Stroke::new(1.0, egui::Color32::WHITE));
+                                        if let Some(text) = arrival_text(flight, now_ms()).filter(|_| self.show_arrival_labels) {
+                                            painter.text(
+                                                ship_pos + egui::vec2(6.0, -6.0),
+                                                egui::Align2::LEFT_BOTTOM,
+                                                text,
+                                                egui::FontId::proportional(10.0),
+                                                flight_color,
+                                            );
+                                        }
+                                    } else {
+                                        // No timestamps, so draw an arrow pointing towards the destination
+                                        let mid = pos_origin + (pos_dest - pos_origin) * 0.6;
+                                        let arrow_size = 8.0;
+                                        let perp = egui::vec2(-dir.y, dir.x);
+                                        
+                                        let arrow_tip = mid + dir * arrow_size;
+                                        let arrow_left = mid - dir * arrow_size * 0.5 + perp * arrow_size * 0.5;
+                                        let arrow_right = mid - dir * arrow_size * 0.5 - perp * arrow_size * 0.5;
+                                        
+                                        painter.add(egui::Shape::convex_polygon(
+                                            vec![arrow_tip, arrow_left, arrow_right],
+                                            flight_color,
+                                            egui::Stroke::NONE,
+                                        ));
+                                    }
                                     
-                                    painter.add(egui::Shape::convex_polygon(
-                                        vec![arrow_tip, arrow_left, arrow_right],
-                                        flight_color,
-                                        egui::Stroke::NONE,
-                                    ));
+                                    // Fading trail behind the ship's current position
+                                    if let Some(t) = progress.filter(|_| self.show_ship_trails) {
+                                        let head = pos_origin + (pos_dest - pos_origin) * t;
+                                        let travelled = (head - pos_origin).length();
+                                        for i in 0..SHIP_TRAIL_DOTS {
+                                            let back = i as f32 * SHIP_TRAIL_SPACING;
+                                            if back > travelled {
+                                                break;
+                                            }
+                                            let fade = 1.0 - i as f32 / SHIP_TRAIL_DOTS as f32;
+                                            painter.circle_filled(
+                                                head - dir * back,
+                                                1.0 + 2.0 * fade,
+                                                flight_color.gamma_multiply(fade),
+                                            );
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -308,18 +2137,31 @@ impl StarMapApp {
                 }
             }
 
-            // Draw stars
+            // Draw the followed ship on top of its flight line
+            if let Some(position) = followed_position {
+                let pos = self.position_to_screen(position, rect);
+                painter.circle_filled(pos, 4.0, flight_color);
+                painter.circle_stroke(pos, 7.0, egui::Stroke::new(1.5, egui::Color32::WHITE));
+            }
+
+            // Draw stars, furthest first in the 3D view so nearer stars end up on top
+            let mut draw_order: Vec<NodeIndex> = star_map.graph.node_indices().collect();
+            if self.view.projection == Projection::Perspective3D {
+                draw_order.sort_by(|a, b| depths[a.index()].1.total_cmp(&depths[b.index()].1));
+            }
             let mut new_hovered = None;
-            for node_idx in star_map.graph.node_indices() {
+            let mut visible_sectors = HashSet::new();
+            for node_idx in draw_order {
                 let node = &star_map.graph[node_idx];
-                let pos = self.world_to_screen(node, rect);
+                let pos = screen_pos(node_idx);
 
-                if !rect.contains(pos) {
+                if !rect.contains(pos) || !is_shown(node_idx) {
                     continue;
                 }
 
-                let base_radius = 3.0 + self.view.zoom * 2.0;
-                let is_selected = self.selected_star == Some(node_idx);
+                let depth_scale = depths[node_idx.index()].0.clamp(0.6, 1.6);
+                let base_radius = (3.0 + self.view.zoom * 2.0) * depth_scale * node.star_type.size_scale();
+                let is_selected = self.selected_star == Some(node_idx) || self.selected_stars.contains(&node_idx);
                 let is_hovered = self.hovered_star == Some(node_idx);
 
                 let radius = if is_selected {
@@ -330,7 +2172,22 @@ impl StarMapApp {
                     base_radius
                 };
 
-                let star_color = node.star_type.color();
+                let focused = in_focus(node);
+                // Closer systems are brighter; anything out of range fades towards the background
+                let range_brightness = jump_range.as_ref().map_or(1.0, |range| match range.get(&node_idx) {
+                    Some(&jumps) => 1.0 - (1.0 - RANGE_EDGE_BRIGHTNESS) * jumps as f32 / self.jump_range as f32,
+                    None => OUT_OF_RANGE_DIM_FACTOR,
+                });
+                let dim = |color: egui::Color32| {
+                    let color = color.gamma_multiply(range_brightness);
+                    if focused { color } else { color.gamma_multiply(FOCUS_DIM_FACTOR) }
+                };
+                let star_color = if self.color_by_sector {
+                    dim(sector_color(&node.sector_id))
+                } else {
+                    dim(node.star_type.color())
+                };
+                visible_sectors.insert(node.sector_id.as_str());
 
                 // Check for hover
                 if let Some(hover_pos) = response.hover_pos() {
@@ -350,27 +2207,57 @@ impl StarMapApp {
 
                 // Check for system markers (can be multiple stacked rings)
                 let markers = self.system_markers.get(&node.natural_id);
+                let stl_only_ships = self.user_data.as_ref().is_some_and(|user_data| {
+                    user_data.ship_system_ids.contains(&node.natural_id)
+                        && !user_data.ftl_ship_system_ids.contains(&node.natural_id)
+                });
                 
+                // Ring size and spacing follow the star radius so rings hug the star at any zoom
+                let ring_width = (radius * 0.35).clamp(1.5, 4.0);
+                let ring_gap = ring_width * 0.4;
+                let ring_offset = radius * 0.4 + ring_gap + ring_width * 0.5;
+
+                // Compact mode: a small row of dots above and to the right of the star
+                if let Some(markers) = markers.filter(|_| self.compact_markers) {
+                    let dot_radius = 2.0;
+                    let start = pos + egui::vec2(radius + 2.0, -radius - 2.0);
+                    for (i, marker) in markers.iter().enumerate() {
+                        let center = start + egui::vec2(i as f32 * (dot_radius * 2.0 + 1.0), 0.0);
+                        let color = dim(marker.color(self.color_blind_markers));
+                        if *marker == SystemMarker::Ship && stl_only_ships {
+                            painter.circle_stroke(center, dot_radius, egui::Stroke::new(1.0, color));
+                        } else {
+                            painter.circle_filled(center, dot_radius, color);
+                        }
+                    }
+                }
                 // Draw stacked marker rings if present (outer to inner: CX -> Base -> Ship)
-                if let Some(markers) = markers {
-                    let ring_width = 2.5;
-                    let ring_gap = 1.0;
-                    
+                else if let Some(markers) = markers {
                     // Draw rings from outside in
                     for (i, marker) in markers.iter().enumerate() {
-                        let marker_color = marker.color();
-                        let ring_radius = radius + 3.0 + (markers.len() - 1 - i) as f32 * (ring_width + ring_gap);
+                        let marker_color = dim(marker.color(self.color_blind_markers));
+                        let ring_radius = radius + ring_offset + (markers.len() - 1 - i) as f32 * (ring_width + ring_gap);
+                        let stroke = if *marker == SystemMarker::Ship && stl_only_ships {
+                            // Ships that can't leave the system get a thin, faded ring
+                            egui::Stroke::new(ring_width * 0.5, marker_color.gamma_multiply(0.6))
+                        } else {
+                            egui::Stroke::new(ring_width, marker_color)
+                        };
                         
-                        painter.circle_stroke(
-                            pos,
-                            ring_radius,
-                            egui::Stroke::new(ring_width, marker_color),
-                        );
+                        // Dashes tell markers apart without relying on color alone
+                        match marker.ring_dashes() {
+                            dashes if self.color_blind_markers && dashes > 0 => {
+                                painter.extend(dashed_circle(pos, ring_radius, stroke, dashes));
+                            }
+                            _ => {
+                                painter.circle_stroke(pos, ring_radius, stroke);
+                            }
+                        }
                     }
                     
                     // Draw inner glow using the innermost marker's color
                     if let Some(innermost) = markers.last() {
-                        let glow_color = innermost.color();
+                        let glow_color = dim(innermost.color(self.color_blind_markers));
                         painter.circle_filled(
                             pos,
                             radius + 1.0,
@@ -381,18 +2268,50 @@ impl StarMapApp {
 
                 painter.circle_filled(pos, radius, star_color);
 
+                if self.highlighted_star_types.contains(&node.star_type) {
+                    painter.circle_stroke(pos, radius + 3.0, egui::Stroke::new(1.5, node.star_type.color()));
+                }
+                if hubs.contains(&node_idx) {
+                    painter.circle_stroke(pos, radius + 6.0, egui::Stroke::new(2.0, egui::Color32::from_rgb(200, 120, 255)));
+                }
+
+                // Flag systems whose position data looks wrong
+                if self.highlight_suspicious && star_map.stats.suspicious_nodes.binary_search(&node_idx).is_ok() {
+                    let cross = radius + 4.0;
+                    let stroke = egui::Stroke::new(1.5, egui::Color32::RED);
+                    painter.line_segment([pos - egui::vec2(cross, cross), pos + egui::vec2(cross, cross)], stroke);
+                    painter.line_segment([pos - egui::vec2(cross, -cross), pos + egui::vec2(cross, -cross)], stroke);
+                }
+
                 // Draw label
                 let has_markers = markers.is_some();
-                if self.show_labels || is_hovered || is_selected || has_markers {
-                    let label_text = if let Some(cx_name) = self.cx_names.get(&node.natural_id) {
-                        format!("{} ({})", node.name, cx_name)
+                if is_hovered || is_selected || (focused && (self.show_labels || has_markers)) {
+                    let name = match self.label_content {
+                        LabelContent::Name => node.name.clone(),
+                        LabelContent::NaturalId => node.natural_id.clone(),
+                        LabelContent::Both => format!("{} {}", node.natural_id, node.name),
+                    };
+                    let mut label_text = if let Some(cx_name) = self.cx_names.get(&node.natural_id) {
+                        format!("{} ({})", name, cx_name)
                     } else {
-                        node.name.clone()
+                        name
                     };
+                    if let Some(reference) = self.reference_star {
+                        match self.reference_metric {
+                            DistanceMetric::Jumps => match self.reference_jumps.get(&node_idx) {
+                                Some(jumps) => label_text.push_str(&format!(" [{}j]", jumps)),
+                                None => label_text.push_str(" [–]"),
+                            },
+                            DistanceMetric::Euclidean => {
+                                let distance = star_map.graph[reference].distance_to(node);
+                                label_text.push_str(&format!(" [{:.1}]", distance));
+                            }
+                        }
+                    }
                     
                     // Offset label based on number of rings
-                    let label_offset = if let Some(m) = markers {
-                        radius + 5.0 + m.len() as f32 * 3.5
+                    let label_offset = if let Some(m) = markers.filter(|_| !self.compact_markers) {
+                        radius + ring_offset + m.len() as f32 * (ring_width + ring_gap) + 2.0
                     } else {
                         radius + 5.0
                     };
@@ -408,185 +2327,1508 @@ impl StarMapApp {
             }
 
             self.hovered_star = new_hovered;
+            
+            // Quick facts about the hovered star, next to the cursor
+            if let Some(idx) = self.hovered_star.filter(|_| !response.dragged()) {
+                let node = &star_map.graph[idx];
+                response.clone().on_hover_ui_at_pointer(|ui| {
+                    ui.strong(&node.name);
+                    ui.label(format!("{} · {:?} ({})", node.natural_id, node.star_type, node.star_type.description()));
+                    for &marker in self.system_markers.get(&node.natural_id).into_iter().flatten() {
+                        ui.colored_label(marker.color(self.color_blind_markers), self.marker_label(marker, &node.natural_id));
+                    }
+                });
+            }
+            let mut visible_sectors: Vec<String> = visible_sectors.into_iter().map(str::to_string).collect();
+            visible_sectors.sort();
+            self.visible_sectors = visible_sectors;
 
-            // Handle click selection
+            // Handle click selection (shift-click plans a route from the selected star,
+            // ctrl-click adds or removes a star from the multi-selection)
             if response.clicked() {
-                self.selected_star = self.hovered_star;
+                let (shift, ctrl) = ui.input(|i| (i.modifiers.shift, i.modifiers.command));
+                let clicked_star = match response.interact_pointer_pos() {
+                    Some(click_pos) if self.click_nearest_star && self.hovered_star.is_none() => {
+                        self.nearest_star_on_screen(&star_map, click_pos, rect, NEAREST_CLICK_RADIUS)
+                    }
+                    _ => self.hovered_star,
+                };
+                match (shift, self.selected_star, clicked_star) {
+                    (false, _, Some(idx)) if ctrl => {
+                        // Start the set from the single selection so it isn't lost
+                        if self.selected_stars.is_empty() {
+                            self.selected_stars.extend(self.selected_star);
+                        }
+                        if self.selected_stars.remove(&idx) {
+                            if self.selected_star == Some(idx) {
+                                self.selected_star = None;
+                            }
+                        } else {
+                            self.selected_stars.insert(idx);
+                            self.selected_star = Some(idx);
+                        }
+                    }
+                    (true, Some(start), Some(end)) => {
+                        self.route = star_map.shortest_path(start, end);
+                        self.unreachable_route = self.route.is_none().then_some((start, end));
+                    }
+                    _ => {
+                        self.selected_star = clicked_star;
+                    }
+                }
+            }
+            
+            // Double-clicking a star flies the view to it
+            if response.double_clicked() {
+                if let Some(idx) = self.hovered_star {
+                    self.selected_star = Some(idx);
+                    self.following_ship = None;
+                    self.view.fly_to(star_map.graph[idx].position, self.view.zoom.max(DOUBLE_CLICK_ZOOM));
+                }
+            }
+            
+            self.draw_scale_bar(&painter, rect, response.hover_pos());
+            
+            if self.show_minimap && self.view.projection != Projection::Perspective3D {
+                self.draw_minimap(ui, &painter, rect, &star_map);
+            }
+        }
+    }
+    
+    // Overview of the whole galaxy in the corner of the map, with the visible region outlined.
+    // Clicking or dragging in it moves the main view there.
+    fn draw_minimap(&mut self, ui: &mut egui::Ui, painter: &egui::Painter, map_rect: egui::Rect, star_map: &StarMap) {
+        let minimap_rect = egui::Rect::from_min_size(
+            map_rect.right_bottom() - MINIMAP_SIZE - egui::Vec2::splat(MINIMAP_MARGIN),
+            MINIMAP_SIZE,
+        );
+        
+        let projected: Vec<egui::Pos2> = star_map.graph.node_weights()
+            .map(|node| {
+                let (x, y) = self.view.project(node.position);
+                egui::pos2(x, y)
+            })
+            .collect();
+        let mut min = egui::pos2(f32::INFINITY, f32::INFINITY);
+        let mut max = egui::pos2(f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for &point in &projected {
+            min = min.min(point);
+            max = max.max(point);
+        }
+        if min.x > max.x {
+            return;
+        }
+        
+        let inner = minimap_rect.shrink(4.0);
+        let extent = (max - min).max(egui::vec2(1.0, 1.0));
+        let scale = (inner.width() / extent.x).min(inner.height() / extent.y);
+        let galaxy_center = min + extent * 0.5;
+        let to_minimap = |point: egui::Pos2| inner.center() + (point - galaxy_center) * scale;
+        
+        painter.rect_filled(minimap_rect, 4.0, egui::Color32::from_rgba_unmultiplied(0, 0, 0, 200));
+        painter.rect_stroke(minimap_rect, 4.0, egui::Stroke::new(1.0, egui::Color32::GRAY));
+        for point in projected {
+            painter.rect_filled(
+                egui::Rect::from_center_size(to_minimap(point), egui::vec2(1.0, 1.0)),
+                0.0,
+                egui::Color32::from_gray(170),
+            );
+        }
+        
+        // The part of the galaxy currently on screen
+        let (offset, zoom) = (self.view.offset, self.view.zoom);
+        let to_projected = |screen: egui::Pos2| ((screen - map_rect.center() - offset) / zoom).to_pos2();
+        let viewport = egui::Rect::from_two_pos(
+            to_minimap(to_projected(map_rect.min)),
+            to_minimap(to_projected(map_rect.max)),
+        );
+        painter.with_clip_rect(minimap_rect).rect_stroke(viewport, 0.0, egui::Stroke::new(1.0, egui::Color32::YELLOW));
+        
+        let response = ui.interact(minimap_rect, ui.id().with("minimap"), egui::Sense::click_and_drag());
+        if response.clicked() || response.dragged() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let target = galaxy_center + (pos - inner.center()) / scale;
+                self.view.offset = -target.to_vec2() * zoom;
+                self.following_ship = None;
             }
         }
     }
 
     fn draw_sidebar(&mut self, ui: &mut egui::Ui) {
-        ui.heading("Star Map Controls");
+        ui.horizontal(|ui| {
+            ui.heading("Star Map Controls");
+            if ui.small_button(format!("v{}", APP_VERSION)).on_hover_text("What's new").clicked() {
+                self.show_whats_new = true;
+            }
+        });
         ui.separator();
 
         // Loading/status
         if self.loading {
-            ui.spinner();
-            ui.label("Loading star data...");
+            let elapsed_ms = now_ms() - self.loading_started_ms;
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Loading star data... {:.0}s", elapsed_ms / 1000.0));
+            });
+            match self.star_download {
+                // Content-Length is the compressed size, so only trust it while we're still below it
+                Some(api::DownloadProgress { received, total: Some(total) }) if total > 0 && received <= total => {
+                    ui.add(
+                        egui::ProgressBar::new(received as f32 / total as f32)
+                            .text(format!("{} of {}", format_bytes(received), format_bytes(total))),
+                    );
+                }
+                Some(api::DownloadProgress { received, .. }) => {
+                    ui.label(format!("Downloaded {}", format_bytes(received)));
+                }
+                None => {}
+            }
+            if self.star_download.is_some_and(|progress| progress.received >= LARGE_DOWNLOAD_BYTES) {
+                ui.small("The full star catalog is a large download; slow connections may take a while.");
+            }
+            if elapsed_ms > SLOW_LOAD_WARNING_MS {
+                ui.colored_label(egui::Color32::YELLOW, "This is taking longer than usual…");
+                if ui.button("🔄 Retry").clicked() {
+                    self.star_systems_requested = true;
+                    self.bypass_star_cache = true;
+                }
+            }
         } else if let Some(error) = &self.error {
             ui.colored_label(egui::Color32::RED, format!("Error: {}", error));
         } else if let Some(star_map) = &self.star_map {
             ui.label(format!("Stars: {}", star_map.node_count()));
             ui.label(format!("Connections: {}", star_map.edge_count()));
             ui.label(format!("CX Stations: {}", self.cx_system_ids.len()));
+            if self.skipped_systems > 0 {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    format!("⚠ {} malformed systems skipped", self.skipped_systems),
+                );
+            }
+            if ui.small_button("🔄 Refresh star data").on_hover_text("Fetch star systems again instead of using the cached copy").clicked() {
+                self.star_systems_requested = true;
+                self.bypass_star_cache = true;
+            }
+            
+            let stats = &star_map.stats;
+            egui::CollapsingHeader::new("Statistics")
+                .id_salt("statistics")
+                .show(ui, |ui| {
+                    egui::Grid::new("statistics_grid").show(ui, |ui| {
+                        ui.label("Systems:");
+                        ui.label(star_map.node_count().to_string());
+                        ui.end_row();
+                        ui.label("Connections:");
+                        ui.label(star_map.edge_count().to_string());
+                        ui.end_row();
+                        ui.label("Average degree:");
+                        ui.label(format!("{:.2}", stats.average_degree));
+                        ui.end_row();
+                        if let Some((idx, degree)) = stats.max_degree {
+                            ui.label("Max degree:");
+                            let node = &star_map.graph[idx];
+                            if ui.selectable_label(false, format!("{} ({})", degree, node.name)).clicked() {
+                                self.selected_star = Some(idx);
+                                self.view.center_on(node.position);
+                                self.following_ship = None;
+                            }
+                            ui.end_row();
+                        }
+                        ui.label("Components:");
+                        ui.label(star_map.connected_components().len().to_string());
+                        ui.end_row();
+                    });
+                    
+                    // Spectral type histogram, bars scaled to the most common type
+                    let most_common = stats.max_star_type_count.max(1);
+                    for &(star_type, count) in &stats.star_type_counts {
+                        if count == 0 {
+                            continue;
+                        }
+                        ui.horizontal(|ui| {
+                            ui.add_sized([60.0, 14.0], egui::Label::new(format!("{:?}", star_type)));
+                            let width = STAT_BAR_WIDTH * count as f32 / most_common as f32;
+                            let (bar, _) = ui.allocate_exact_size(egui::vec2(STAT_BAR_WIDTH, 10.0), egui::Sense::hover());
+                            let filled = egui::Rect::from_min_size(bar.min, egui::vec2(width, bar.height()));
+                            ui.painter().rect_filled(filled, 2.0, star_type.color());
+                            ui.label(count.to_string());
+                        }).response.on_hover_text(star_type.description());
+                    }
+                });
+        }
+        if ui.small_button("📂 Load from file…").on_hover_text("Use a /systemstars JSON dump instead of the API").clicked() {
+            self.star_file_requested = true;
+            self.import_error = None;
+        }
+        if let Some(error) = &self.import_error {
+            ui.colored_label(egui::Color32::RED, format!("Import failed: {}", error));
+        }
+
+        ui.checkbox(&mut self.show_diagnostics, "Show diagnostics");
+        if self.show_diagnostics {
+            if let Some(star_map) = &self.star_map {
+                let stats = &star_map.stats;
+                let size = stats.bounds.size();
+                egui::Grid::new("diagnostics_grid").show(ui, |ui| {
+                    ui.label("Nodes:");
+                    ui.label(star_map.node_count().to_string());
+                    ui.end_row();
+                    ui.label("Edges:");
+                    ui.label(star_map.edge_count().to_string());
+                    ui.end_row();
+                    ui.label("One-way edges:");
+                    ui.label(stats.one_way_edges.to_string());
+                    ui.end_row();
+                    ui.label("Components:");
+                    ui.label(star_map.connected_components().len().to_string());
+                    ui.end_row();
+                    ui.label("Extent:");
+                    ui.label(format!("{:.0} × {:.0} × {:.0}", size[0], size[1], size[2]));
+                    ui.end_row();
+                });
+                
+                egui::CollapsingHeader::new(format!("Connected components ({})", star_map.connected_components().len()))
+                    .id_salt("components")
+                    .show(ui, |ui| {
+                        let components = star_map.connected_components();
+                        let isolated = components.iter().filter(|c| c.len() == 1).count();
+                        if let Some(main) = components.first() {
+                            ui.label(format!("Main cluster: {} systems, {} isolated systems", main.len(), isolated));
+                        }
+                        egui::ScrollArea::vertical()
+                            .id_salt("components_scroll")
+                            .max_height(150.0)
+                            .show(ui, |ui| {
+                                for (i, component) in components.iter().enumerate() {
+                                    let first = &star_map.graph[component[0]];
+                                    let text = match component.len() {
+                                        1 => format!("#{}: {} (isolated)", i + 1, first.name),
+                                        n => format!("#{}: {} systems, incl. {}", i + 1, n, first.name),
+                                    };
+                                    if ui.selectable_label(false, text).clicked() {
+                                        let positions = component.iter().map(|&idx| star_map.graph[idx].position);
+                                        self.view.fit(positions, self.map_size);
+                                        self.following_ship = None;
+                                        if component.len() == 1 {
+                                            self.selected_star = Some(component[0]);
+                                        }
+                                    }
+                                }
+                            });
+                    });
+                
+                if ui.button("📋 Copy stats").on_hover_text("Copy galaxy statistics to the clipboard").clicked() {
+                    ui.ctx().copy_text(star_map.summary_text(self.cx_system_ids.len()));
+                }
+                
+                ui.checkbox(
+                    &mut self.highlight_suspicious,
+                    format!("Highlight suspicious positions ({})", stats.suspicious_nodes.len()),
+                );
+                if self.highlight_suspicious && !stats.suspicious_nodes.is_empty() {
+                    egui::ScrollArea::vertical()
+                        .id_salt("suspicious_positions")
+                        .max_height(120.0)
+                        .show(ui, |ui| {
+                            for &idx in &stats.suspicious_nodes {
+                                let node = &star_map.graph[idx];
+                                let text = format!(
+                                    "{} ({:.1}, {:.1}, {:.1})",
+                                    node.natural_id, node.position[0], node.position[1], node.position[2],
+                                );
+                                if ui.selectable_label(self.selected_star == Some(idx), text).clicked() {
+                                    self.selected_star = Some(idx);
+                                }
+                            }
+                        });
+                }
+            }
+        }
+
+        ui.separator();
+
+        // Projection selection
+        ui.label("Projection:");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.view.projection, Projection::XY, "X-Y");
+            ui.selectable_value(&mut self.view.projection, Projection::XZ, "X-Z");
+            ui.selectable_value(&mut self.view.projection, Projection::YZ, "Y-Z");
+            ui.selectable_value(&mut self.view.projection, Projection::Perspective3D, "3D");
+        });
+        ui.checkbox(&mut self.animate_projection, "Animate projection changes");
+        ui.checkbox(&mut self.normalize_axes, "Normalize axes")
+            .on_hover_text("Stretch each axis to the same range. Distorts true distances.");
+        if self.normalize_axes {
+            ui.small("⚠ Axes normalized: distances are distorted");
+        }
+        ui.small("Press P to cycle projections, +/- to zoom");
+        ui.small("WASD to pan; arrow keys or Tab move to a connected system");
+        ui.small("Shift+click plans a route, Ctrl+click selects several systems");
+        if self.view.projection == Projection::Perspective3D {
+            ui.small("Drag to rotate the 3D view");
+        }
+
+        ui.separator();
+
+        // View options
+        let mut settings_changed = ui.checkbox(&mut self.show_connections, "Show connections").changed();
+        ui.add_enabled_ui(self.show_connections, |ui| {
+            ui.checkbox(&mut self.sparse_connections_while_dragging, "Simplify connections while panning");
+            ui.checkbox(&mut self.curved_connections, "Curved connections");
+            ui.checkbox(&mut self.directed_connections, "Show connection direction")
+                .on_hover_text("Draw arrows as listed by each system; one-way links are highlighted");
+            ui.add(egui::Slider::new(&mut self.connection_width, 0.25..=4.0).text("Line width"));
+            ui.add(egui::Slider::new(&mut self.connection_opacity, 0.05..=1.0).text("Line opacity"));
+            ui.checkbox(&mut self.scale_connections_with_zoom, "Scale lines with zoom");
+        });
+        ui.checkbox(&mut self.show_minimap, "Show minimap");
+        settings_changed |= ui.checkbox(&mut self.show_grid, "Show grid").changed();
+        ui.checkbox(&mut self.show_sectors, "Show sectors");
+        ui.checkbox(&mut self.color_by_sector, "Color stars by sector");
+        if self.color_by_sector && !self.visible_sectors.is_empty() {
+            egui::CollapsingHeader::new(format!("Sectors in view ({})", self.visible_sectors.len()))
+                .id_salt("sector_legend")
+                .show(ui, |ui| {
+                    egui::ScrollArea::vertical().id_salt("sector_legend_scroll").max_height(150.0).show(ui, |ui| {
+                        for sector_id in &self.visible_sectors {
+                            ui.colored_label(sector_color(sector_id), format!("● {}", sector_id));
+                        }
+                    });
+                });
+        }
+        self.draw_star_type_legend(ui);
+        settings_changed |= ui.checkbox(&mut self.show_labels, "Show all labels").changed();
+        egui::ComboBox::from_label("Label content")
+            .selected_text(self.label_content.label())
+            .show_ui(ui, |ui| {
+                for content in [LabelContent::Name, LabelContent::NaturalId, LabelContent::Both] {
+                    settings_changed |= ui.selectable_value(&mut self.label_content, content, content.label()).changed();
+                }
+            });
+
+        ui.separator();
+        
+        // Marker visibility
+        ui.label("Show markers:");
+        let mut markers_changed = false;
+        let emoji = |marker: SystemMarker| marker.emoji(self.color_blind_markers);
+        let (cx_emoji, base_emoji) = (emoji(SystemMarker::CommodityExchange), emoji(SystemMarker::Base));
+        let (warehouse_emoji, ship_emoji) = (emoji(SystemMarker::Warehouse), emoji(SystemMarker::Ship));
+        markers_changed |= ui.checkbox(&mut self.show_cx, format!("{} Commodity Exchanges", cx_emoji)).changed();
+        ui.indent("cx_toggles", |ui| {
+            ui.add_enabled_ui(self.show_cx, |ui| {
+                markers_changed |= ui.checkbox(&mut self.hide_cx_in_base_systems, "Exclude my systems").changed();
+            });
+        });
+        markers_changed |= ui.checkbox(&mut self.show_bases, format!("{} Bases", base_emoji)).changed();
+        markers_changed |= ui.checkbox(&mut self.show_warehouses, format!("{} Warehouses", warehouse_emoji)).changed();
+        markers_changed |= ui.checkbox(&mut self.show_ships, format!("{} Ships", ship_emoji)).changed();
+        if self.user_data.is_some() {
+            ui.checkbox(&mut self.show_supply_lanes, "Show supply lanes")
+                .on_hover_text("Shortest route from each of your bases to its nearest exchange");
+            if self.show_supply_lanes {
+                let jumps: usize = self.supply_lanes.iter().map(|lane| lane.len() - 1).sum();
+                ui.small(format!("{} lanes, {} jumps in total", self.supply_lanes.len(), jumps));
+            }
+        }
+        settings_changed |= ui.checkbox(&mut self.only_marked_systems, "Only marked systems")
+            .on_hover_text("Hide stars without any of the markers enabled above")
+            .changed();
+        ui.indent("only_marked_toggles", |ui| {
+            ui.add_enabled_ui(self.only_marked_systems, |ui| {
+                settings_changed |= ui.checkbox(&mut self.only_marked_connections, "Only connections between them").changed();
+            });
+        });
+        ui.indent("flight_toggles", |ui| {
+            ui.add_enabled_ui(self.show_ships, |ui| {
+                ui.checkbox(&mut self.show_inter_system_flights, "Show inter-system flights");
+                markers_changed |= ui.checkbox(&mut self.show_in_system_flights, "Show in-system flights").changed();
+                ui.add_enabled_ui(self.show_inter_system_flights, |ui| {
+                    ui.checkbox(&mut self.show_ship_trails, "Ship trails");
+                    ui.checkbox(&mut self.show_arrival_labels, "Arrival countdowns");
+                });
+            });
+        });
+        
+        if markers_changed {
+            self.markers_dirty = true;
+        }
+        settings_changed |= markers_changed;
+        settings_changed |= ui.checkbox(&mut self.compact_markers, "Compact markers").changed();
+        settings_changed |= ui.checkbox(&mut self.color_blind_markers, "Color-blind mode")
+            .on_hover_text("Use colors that stay distinct with color vision deficiency, and dashed rings")
+            .changed();
+        settings_changed |= ui.checkbox(&mut self.click_nearest_star, "Click selects nearest star")
+            .on_hover_text("Clicking near a star selects it even if it isn't hovered")
+            .changed();
+        if settings_changed {
+            self.save_display_settings();
+        }
+
+        if let (Some(reference), Some(star_map)) = (self.reference_star, &self.star_map) {
+            ui.horizontal(|ui| {
+                ui.label(format!("📍 Distances from {}", star_map.graph[reference].name));
+                if ui.small_button("✖").clicked() {
+                    self.reference_star = None;
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.reference_metric, DistanceMetric::Jumps, "Jumps");
+                ui.selectable_value(&mut self.reference_metric, DistanceMetric::Euclidean, "3D distance");
+            });
+        }
+
+        ui.separator();
+
+        // Zoom controls
+        ui.label(format!("Zoom: {:.2}x", self.view.zoom));
+        ui.horizontal(|ui| {
+            if ui.button("-").clicked() {
+                self.view.zoom_by(0.8, egui::Vec2::ZERO);
+            }
+            if ui.button("+").clicked() {
+                self.view.zoom_by(1.25, egui::Vec2::ZERO);
+            }
+            if ui.button("Reset").clicked() {
+                self.view = MapView::default();
+            }
+            if ui.add_enabled(!self.screenshot_pending, egui::Button::new("📷 Save image")).clicked() {
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::Screenshot(egui::UserData::default()));
+                self.screenshot_pending = true;
+            }
+            if let Some(star_map) = &self.star_map {
+                if ui.button("Fit all").on_hover_text("Frame every star in the current projection").clicked() {
+                    let positions = star_map.graph.node_weights().map(|node| node.position);
+                    self.view.fit(positions, self.map_size);
+                    self.following_ship = None;
+                }
+            }
+        });
+
+        ui.separator();
+
+        self.draw_goto_id(ui);
+        
+        // Search
+        ui.label("Search:");
+        ui.add(egui::TextEdit::singleline(&mut self.search_query).hint_text("Name, ID or sector:<id>"));
+        if ui.checkbox(&mut self.sort_search_by_degree, "Most connected first").changed() {
+            self.last_search_query.clear();
+        }
+        self.update_search_matches(ui.ctx());
+        
+        if !self.search_query.is_empty() {
+            if let Some(star_map) = &self.star_map {
+                if self.search_matches.len() == MAX_SEARCH_RESULTS {
+                    ui.small(format!("Showing the first {} matches", MAX_SEARCH_RESULTS));
+                }
+                egui::ScrollArea::vertical()
+                    .id_salt("search_results")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for &idx in &self.search_matches {
+                            let node = &star_map.graph[idx];
+                            if ui.selectable_label(
+                                self.selected_star == Some(idx),
+                                format!("{} ({} links)", node.name, star_map.degree(idx))
+                            ).on_hover_text(format!("{} · sector {}", node.natural_id, node.sector_id)).clicked() {
+                                self.selected_star = Some(idx);
+                                // Center on selected star
+                                self.view.center_on(node.position);
+                            }
+                        }
+                    });
+            }
+        }
+
+        ui.separator();
+
+        self.draw_follow_panel(ui);
+        self.draw_waypoint_panel(ui);
+        self.draw_route_panel(ui);
+        self.draw_focus_panel(ui);
+        self.draw_selection_panel(ui);
+        self.draw_hub_panel(ui);
+        self.draw_resource_panel(ui);
+
+        // Selected star info
+        let mut waypoints_changed = false;
+        let mut price_exchange = None; // Drawn once the star map borrow ends
+        let mut planets_to_request = None; // Queued once the star map borrow ends
+        if let Some(selected_idx) = self.selected_star {
+            if let Some(star_map) = &self.star_map {
+                let node = &star_map.graph[selected_idx];
+                ui.heading(&node.name);
+                ui.label(format!("ID: {}", node.natural_id));
+                ui.label(format!("Type: {:?}", node.star_type));
+                ui.label(format!("Position: ({:.1}, {:.1}, {:.1})", 
+                    node.position[0], node.position[1], node.position[2]));
+                ui.label(format!("Sector: {}", node.sector_id));
+                
+                if ui.button("🎯 Set as focus center").clicked() {
+                    self.focus_center = Some(selected_idx);
+                }
+                ui.checkbox(&mut self.show_reach_hulls, format!("Outline reach (≤{} jumps)", REACH_HULL_DEPTH));
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.show_jump_range, "Jump range");
+                    ui.add_enabled(self.show_jump_range, egui::Slider::new(&mut self.jump_range, 1..=15));
+                });
+                if self.jump_range_source == Some((selected_idx, self.jump_range)) {
+                    let reachable = self.jump_range_counts.len() - 1;
+                    ui.label(format!("{} systems within {} jumps", reachable, self.jump_range));
+                }
+                if ui.button("📍 Set as distance reference").clicked() {
+                    self.reference_star = Some(selected_idx);
+                }
+                if ui.button("➕ Add as waypoint").clicked() {
+                    self.waypoints.push(selected_idx);
+                    waypoints_changed = true;
+                }
+                
+                // Straight-line distance to the star picked with "Measure from here"
+                match self.measure_from {
+                    Some(from) if from != selected_idx => {
+                        let from_node = &star_map.graph[from];
+                        ui.horizontal(|ui| {
+                            ui.label(format!("📏 {:.1} units from {}", node.distance_to(from_node), from_node.name));
+                            if ui.small_button("✖").on_hover_text("Stop measuring").clicked() {
+                                self.measure_from = None;
+                            }
+                        });
+                    }
+                    _ => {
+                        if ui.button("📏 Measure from here").on_hover_text("Select another star to see the straight-line distance").clicked() {
+                            self.measure_from = Some(selected_idx);
+                        }
+                    }
+                }
+                
+                match self.system_planets.get(&node.natural_id) {
+                    Some(planets) => {
+                        egui::CollapsingHeader::new(format!("🪐 Planets ({})", planets.len()))
+                            .id_salt("selected_planets")
+                            .show(ui, |ui| {
+                                for planet in planets {
+                                    let natural_id = planet.planet_natural_id.as_deref().unwrap_or("?");
+                                    match planet.planet_name.as_deref() {
+                                        Some(name) if name != natural_id => ui.label(format!("{} ({})", name, natural_id)),
+                                        _ => ui.label(natural_id),
+                                    };
+                                }
+                            });
+                    }
+                    None => {
+                        if let Some(error) = self.planet_errors.get(&node.natural_id) {
+                            ui.colored_label(egui::Color32::RED, format!("Failed to load planets: {}", error));
+                            if ui.small_button("Retry").clicked() {
+                                self.planet_errors.remove(&node.natural_id);
+                                self.planets_requested.remove(&node.natural_id);
+                            }
+                        } else {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Loading planets...");
+                            });
+                            planets_to_request = Some(node.natural_id.clone());
+                        }
+                    }
+                }
+                
+                // Resources available on this system's planets
+                if let Some(resources) = self.system_resources.get(&node.natural_id) {
+                    egui::CollapsingHeader::new(format!("⛏ Resources ({})", resources.len()))
+                        .id_salt("selected_resources")
+                        .show(ui, |ui| {
+                            for (ticker, deposits) in resources {
+                                let planets: Vec<String> = deposits.iter()
+                                    .map(|d| format!("{} {:.0}%", d.planet_name, d.factor * 100.0))
+                                    .collect();
+                                ui.label(format!("{}: {}", ticker, planets.join(", ")));
+                            }
+                        });
+                }
+                
+                // Show marker info (all markers for this system)
+                if let Some(markers) = self.system_markers.get(&node.natural_id) {
+                    for marker in markers {
+                        let marker_text = self.marker_label(*marker, &node.natural_id);
+                        ui.horizontal(|ui| {
+                            ui.colored_label(marker.color(self.color_blind_markers), marker_text);
+                            match marker {
+                                SystemMarker::CommodityExchange => {
+                                    // The in-game CX command opens the exchange directly
+                                    if let Some(cx_name) = self.cx_names.get(&node.natural_id) {
+                                        if ui.small_button("📋 CX command")
+                                            .on_hover_text("Copy the command that opens this exchange in-game")
+                                            .clicked()
+                                        {
+                                            ui.ctx().copy_text(format!("CX {}", cx_name));
+                                        }
+                                    }
+                                }
+                                SystemMarker::Ship => {
+                                    if ui.small_button("🚀 Show ships").clicked() {
+                                        self.ship_system_filter = Some(node.natural_id.clone());
+                                        self.reveal_ship_list = true;
+                                    }
+                                }
+                                SystemMarker::Base | SystemMarker::Warehouse => {}
+                            }
+                        });
+                    }
+                }
+                
+                price_exchange = self.cx_names.get(&node.natural_id).cloned();
+                
+                // Full segment list for flights passing through this system
+                if let Some(user_data) = &self.user_data {
+                    for flight in user_data.flights.iter().filter(|f| f.passes_through(&node.natural_id)) {
+                        let registration = user_data.ships.iter()
+                            .find(|ship| Some(&ship.ship_id) == flight.ship_id.as_ref())
+                            .map(|ship| ship.registration.as_str())
+                            .or(flight.ship_id.as_deref())
+                            .unwrap_or("Unknown ship");
+                        egui::CollapsingHeader::new(format!("✈ {} route", registration))
+                            .id_salt(("flight_segments", &flight.flight_id))
+                            .show(ui, |ui| {
+                                for (i, segment) in flight.segments.iter().flatten().enumerate() {
+                                    let current = flight.current_segment_index == Some(i as i32);
+                                    let text = format!(
+                                        "{} {}: {} → {}",
+                                        if current { "▶" } else { "  " },
+                                        segment.segment_type.as_deref().unwrap_or("?"),
+                                        segment.origin.as_deref().unwrap_or("?"),
+                                        segment.destination.as_deref().unwrap_or("?"),
+                                    );
+                                    if current {
+                                        ui.strong(text);
+                                    } else {
+                                        ui.label(text);
+                                    }
+                                }
+                            });
+                    }
+                }
+                
+                // Show production buttons for bases in this system
+                if let Some(user_data) = &self.user_data {
+                    let system_id = &node.natural_id;
+                    let bases_in_system: Vec<_> = user_data.base_production.iter()
+                        .filter(|b| extract_system_from_planet(&b.planet_natural_id) == *system_id)
+                        .collect();
+                    
+                    if !bases_in_system.is_empty() {
+                        ui.separator();
+                        ui.heading("📊 Production Rates");
+                        
+                        for base in &bases_in_system {
+                            if ui.button(format!("🏭 {}", base.planet_name)).clicked() {
+                                self.production_windows_open.insert(base.planet_natural_id.clone());
+                            }
+                        }
+                    }
+                    
+                    // Rank the exchanges reachable from this base by jump count
+                    if user_data.base_system_ids.contains(system_id) && !self.cx_system_ids.is_empty() {
+                        ui.separator();
+                        ui.heading("🏪 Nearest exchanges");
+                        for &(jumps, cx_idx) in &self.nearest_exchanges {
+                            let cx_node = &star_map.graph[cx_idx];
+                            let name = self.cx_names.get(&cx_node.natural_id).unwrap_or(&cx_node.name);
+                            if ui.selectable_label(false, format!("{} ({} jumps)", name, jumps))
+                                .on_hover_text("Show the route to this exchange")
+                                .clicked()
+                            {
+                                self.route = star_map.shortest_path(selected_idx, cx_idx);
+                            }
+                        }
+                    }
+                }
+
+                // Show connections
+                ui.separator();
+                let neighbors: Vec<_> = star_map.graph.neighbors(selected_idx).collect();
+                if !neighbors.is_empty() {
+                    ui.label(format!("Connections: {}", neighbors.len()));
+                    egui::ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                        for neighbor_idx in neighbors {
+                            let neighbor = &star_map.graph[neighbor_idx];
+                            let label = format!("{} ({} links)", neighbor.name, star_map.degree(neighbor_idx));
+                            if ui.selectable_label(false, label).clicked() {
+                                self.selected_star = Some(neighbor_idx);
+                            }
+                        }
+                    });
+                }
+                
+                // Systems a few jumps further out, grouped by distance (direct neighbors are listed above)
+                let mut nearby: Vec<(usize, NodeIndex)> = star_map.reachable_within(selected_idx, NEARBY_JUMPS)
+                    .into_iter()
+                    .filter(|&(_, jumps)| jumps > 1)
+                    .map(|(idx, jumps)| (jumps, idx))
+                    .collect();
+                if !nearby.is_empty() {
+                    nearby.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| star_map.graph[a.1].name.cmp(&star_map.graph[b.1].name)));
+                    egui::CollapsingHeader::new(format!("Nearby systems ({})", nearby.len()))
+                        .id_salt("nearby_systems")
+                        .show(ui, |ui| {
+                            egui::ScrollArea::vertical().id_salt("nearby_scroll").max_height(150.0).show(ui, |ui| {
+                                for jumps in 2..=NEARBY_JUMPS {
+                                    let group: Vec<NodeIndex> = nearby.iter()
+                                        .filter(|&&(j, _)| j == jumps)
+                                        .map(|&(_, idx)| idx)
+                                        .collect();
+                                    if group.is_empty() {
+                                        continue;
+                                    }
+                                    ui.strong(format!("{} jumps", jumps));
+                                    for idx in group {
+                                        if ui.selectable_label(false, &star_map.graph[idx].name).clicked() {
+                                            self.selected_star = Some(idx);
+                                        }
+                                    }
+                                }
+                            });
+                        });
+                }
+            }
+        }
+        if let Some(code) = price_exchange {
+            self.draw_exchange_prices(ui, code);
+        }
+        if let Some(system_id) = planets_to_request {
+            self.request_system_planets(&system_id);
+        }
+        if waypoints_changed {
+            self.update_waypoint_route();
+        }
+    }
+    
+    // Exact, case-insensitive natural ID lookup that selects and centers the system on Enter
+    fn draw_goto_id(&mut self, ui: &mut egui::Ui) {
+        let now = ui.input(|i| i.time);
+        ui.horizontal(|ui| {
+            ui.label("Go to ID:");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.goto_query).hint_text("UV-351").desired_width(80.0));
+            if !(response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
+                return;
+            }
+            let Some(star_map) = &self.star_map else {
+                return;
+            };
+            let query = self.goto_query.trim();
+            if query.is_empty() {
+                return;
+            }
+            let found = star_map.natural_id_to_node.get(&query.to_uppercase()).copied().or_else(|| {
+                let query = query.to_lowercase();
+                star_map.search_index.iter().find(|keys| keys.natural_id == query).map(|keys| keys.node)
+            });
+            match found {
+                Some(idx) => {
+                    self.selected_star = Some(idx);
+                    self.view.center_on(star_map.graph[idx].position);
+                    self.following_ship = None;
+                    self.goto_query.clear();
+                    self.goto_error = None;
+                }
+                None => self.goto_error = Some((format!("No system with ID {}", query), now)),
+            }
+        });
+        
+        if self.goto_error.as_ref().is_some_and(|(_, shown_at)| now - shown_at >= GOTO_ERROR_SECS) {
+            self.goto_error = None;
+        }
+        if let Some((message, shown_at)) = &self.goto_error {
+            ui.colored_label(egui::Color32::RED, message);
+            ui.ctx().request_repaint_after(std::time::Duration::from_secs_f64(GOTO_ERROR_SECS - (now - shown_at)));
+        }
+    }
+    
+    // Recompute search matches once the query has changed and then stayed the same for a
+    // frame, so fast typing doesn't rescan the map on every keystroke
+    fn update_search_matches(&mut self, ctx: &egui::Context) {
+        if self.search_query == self.last_search_query {
+            return;
+        }
+        if self.search_query != self.previous_frame_query {
+            self.previous_frame_query = self.search_query.clone();
+            ctx.request_repaint();
+            return;
+        }
+        self.last_search_query = self.search_query.clone();
+        self.search_matches.clear();
+        
+        if self.search_query.is_empty() {
+            return;
+        }
+        let Some(star_map) = &self.star_map else {
+            return;
+        };
+        
+        // "sector:<id>" searches sector IDs only; otherwise names, natural IDs and sectors all match
+        let query = self.search_query.trim().to_lowercase();
+        let (query, sector_only) = match query.strip_prefix("sector:") {
+            Some(sector) => (sector.trim().to_string(), true),
+            None => (query, false),
+        };
+        if query.is_empty() {
+            return;
+        }
+        
+        // Rank exact matches first, then prefix matches, then substring matches
+        let rank = |field: &str| {
+            if field == query {
+                Some(0)
+            } else if field.starts_with(&query) {
+                Some(1)
+            } else if field.contains(&query) {
+                Some(2)
+            } else {
+                None
+            }
+        };
+        let mut ranked: Vec<(u8, NodeIndex)> = star_map.search_index.iter()
+            .filter_map(|keys| {
+                let best = if sector_only {
+                    rank(&keys.sector_id)
+                } else {
+                    [rank(&keys.name), rank(&keys.natural_id), rank(&keys.sector_id)].into_iter().flatten().min()
+                };
+                best.map(|rank| (rank, keys.node))
+            })
+            .collect();
+        if self.sort_search_by_degree {
+            ranked.sort_by(|a, b| star_map.degree(b.1).cmp(&star_map.degree(a.1)).then(a.0.cmp(&b.0)));
+        } else {
+            ranked.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| star_map.graph[a.1].name.cmp(&star_map.graph[b.1].name)));
+        }
+        self.search_matches = ranked.into_iter()
+            .take(MAX_SEARCH_RESULTS)
+            .map(|(_, idx)| idx)
+            .collect();
+    }
+    
+    // Ticker → ask/bid table for one exchange, fetched the first time it's opened
+    fn draw_exchange_prices(&mut self, ui: &mut egui::Ui, code: String) {
+        egui::CollapsingHeader::new(format!("💰 Prices on {}", code))
+            .id_salt("exchange_prices")
+            .show(ui, |ui| {
+                let Some(all_prices) = &self.exchange_prices else {
+                    if self.loading_exchange_prices {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Loading prices...");
+                        });
+                    } else if let Some(error) = &self.exchange_prices_error {
+                        ui.colored_label(egui::Color32::RED, error);
+                        if ui.button("🔄 Retry").clicked() {
+                            self.exchange_prices_requested = true;
+                        }
+                    } else {
+                        // Only fetched automatically the first time; later loads are explicit refreshes
+                        self.exchange_prices_requested = true;
+                    }
+                    return;
+                };
+                
+                let refresh = egui::Button::new("🔄 Refresh prices").small();
+                if ui.add_enabled(!self.loading_exchange_prices, refresh).clicked() {
+                    self.exchange_prices_requested = true;
+                }
+                if let Some(error) = &self.exchange_prices_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+                let Some(prices) = all_prices.get(&code) else {
+                    ui.label("No prices listed for this exchange");
+                    return;
+                };
+                
+                ui.add(egui::TextEdit::singleline(&mut self.price_filter).hint_text("Filter tickers"));
+                let filter = self.price_filter.trim().to_uppercase();
+                let format_price = |price: Option<f64>| price.map_or("—".to_string(), |price| format!("{:.2}", price));
+                egui::ScrollArea::vertical()
+                    .id_salt("exchange_prices_scroll")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("exchange_prices_grid").striped(true).show(ui, |ui| {
+                            ui.strong("Ticker");
+                            ui.strong("Ask");
+                            ui.strong("Bid");
+                            ui.end_row();
+                            for price in prices.iter().filter(|price| price.material_ticker.contains(&filter)) {
+                                ui.label(&price.material_ticker);
+                                ui.label(format_price(price.ask));
+                                ui.label(format_price(price.bid));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                if let Some(currency) = prices.first().and_then(|price| price.currency.as_deref()) {
+                    ui.small(format!("Prices in {}", currency));
+                }
+            });
+    }
+    
+    fn draw_resource_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label("Planet resources:");
+        
+        if self.loading_resources {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Loading planet data...");
+            });
+        } else if self.resource_tickers.is_empty() {
+            if let Some(error) = &self.resource_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            if ui.button("Load planet resources").clicked() {
+                self.resources_requested = true;
+            }
+        } else {
+            egui::ComboBox::from_id_salt("resource_filter")
+                .selected_text(self.resource_filter.as_deref().unwrap_or("Any resource"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.resource_filter, None, "Any resource");
+                    for ticker in &self.resource_tickers {
+                        ui.selectable_value(&mut self.resource_filter, Some(ticker.clone()), ticker.as_str());
+                    }
+                });
+            
+            if let Some(ticker) = &self.resource_filter {
+                let count = self.system_resources.values()
+                    .filter(|resources| resources.contains_key(ticker))
+                    .count();
+                ui.label(format!("{} systems with {}", count, ticker));
+            }
+        }
+        
+        ui.separator();
+    }
+    
+    fn draw_selection_panel(&mut self, ui: &mut egui::Ui) {
+        let Some(star_map) = &self.star_map else {
+            return;
+        };
+        
+        ui.label("Select:");
+        ui.horizontal_wrapped(|ui| {
+            let current_sector = self.selected_star.map(|idx| star_map.graph[idx].sector_id.clone());
+            ui.add_enabled_ui(current_sector.is_some(), |ui| {
+                if ui.button("Current sector").clicked() {
+                    if let Some(sector) = &current_sector {
+                        self.selected_stars = star_map.graph.node_indices()
+                            .filter(|&idx| star_map.graph[idx].sector_id == *sector)
+                            .collect();
+                    }
+                }
+            });
+            if ui.button("All CX").clicked() {
+                self.selected_stars = self.cx_system_ids.iter()
+                    .filter_map(|id| star_map.natural_id_to_node.get(id).copied())
+                    .collect();
+            }
+            if let Some(user_data) = &self.user_data {
+                if ui.button("My bases").clicked() {
+                    self.selected_stars = user_data.base_system_ids.iter()
+                        .filter_map(|id| star_map.natural_id_to_node.get(id).copied())
+                        .collect();
+                }
+            }
+        });
+        
+        if !self.selected_stars.is_empty() {
+            ui.label(format!("{} systems selected", self.selected_stars.len()));
+            let extent = Bounds::from_positions(self.selected_stars.iter().map(|&idx| star_map.graph[idx].position));
+            if self.selected_stars.len() > 1 {
+                ui.label(format!("Spanning {:.1} units", extent.diagonal()))
+                    .on_hover_text("Diagonal of the box containing the selected systems");
+            }
+            let mut load_planets = false;
+            ui.horizontal(|ui| {
+                if ui.button("Fit view").clicked() {
+                    let positions = self.selected_stars.iter().map(|&idx| star_map.graph[idx].position);
+                    self.view.fit(positions, self.map_size);
+                }
+                load_planets = ui.button("🪐 Load planets").clicked();
+                if ui.button("Clear").clicked() {
+                    self.selected_stars.clear();
+                }
+            });
+            
+            let system_ids: Vec<String> = self.selected_stars.iter()
+                .map(|&idx| star_map.graph[idx].natural_id.clone())
+                .collect();
+            let loaded = system_ids.iter().filter(|id| self.system_planets.contains_key(*id)).count();
+            if loaded > 0 {
+                ui.label(format!("Planets loaded for {}/{} systems", loaded, system_ids.len()));
+            }
+            if load_planets {
+                for system_id in &system_ids {
+                    self.request_system_planets(system_id);
+                }
+            }
         }
-
+        
         ui.separator();
-
-        // Projection selection
-        ui.label("Projection:");
+    }
+    
+    fn draw_follow_panel(&mut self, ui: &mut egui::Ui) {
+        let Some(ship_id) = &self.following_ship else {
+            return;
+        };
+        
+        let registration = self.user_data.as_ref()
+            .and_then(|ud| ud.flight_paths.iter().find(|f| f.ship_id.as_ref() == Some(ship_id)))
+            .and_then(|f| f.ship_registration.clone())
+            .unwrap_or_else(|| ship_id.clone());
+        
         ui.horizontal(|ui| {
-            ui.selectable_value(&mut self.view.projection, Projection::XY, "X-Y");
-            ui.selectable_value(&mut self.view.projection, Projection::XZ, "X-Z");
-            ui.selectable_value(&mut self.view.projection, Projection::YZ, "Y-Z");
+            ui.label(format!("👁 Following {}", registration));
+            if ui.button("Stop following").clicked() {
+                self.following_ship = None;
+            }
         });
-
         ui.separator();
-
-        // View options
-        ui.checkbox(&mut self.show_connections, "Show connections");
-        ui.checkbox(&mut self.show_labels, "Show all labels");
-
+    }
+    
+    // What the star colors mean; clicking a type highlights its stars on the map
+    fn draw_star_type_legend(&mut self, ui: &mut egui::Ui) {
+        let Some(star_map) = &self.star_map else {
+            return;
+        };
+        
+        egui::CollapsingHeader::new("⭐ Star types")
+            .id_salt("star_type_legend")
+            .show(ui, |ui| {
+                for &(star_type, count) in &star_map.stats.star_type_counts {
+                    let highlighted = self.highlighted_star_types.contains(&star_type);
+                    ui.horizontal(|ui| {
+                        let (swatch, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                        ui.painter().circle_filled(swatch.center(), 5.0, star_type.color());
+                        let text = format!("{:?} — {} ({})", star_type, star_type.description(), count);
+                        if ui.selectable_label(highlighted, text).on_hover_text("Highlight on the map").clicked() {
+                            if highlighted {
+                                self.highlighted_star_types.remove(&star_type);
+                            } else {
+                                self.highlighted_star_types.insert(star_type);
+                            }
+                        }
+                    });
+                }
+            });
+    }
+    
+    fn draw_hub_panel(&mut self, ui: &mut egui::Ui) {
+        let Some(star_map) = &self.star_map else {
+            return;
+        };
+        
+        egui::CollapsingHeader::new("🔗 Top hubs")
+            .id_salt("top_hubs")
+            .show(ui, |ui| {
+                ui.checkbox(&mut self.show_hubs, "Highlight on map");
+                let scores = self.hub_scores.get_or_insert_with(|| star_map.hub_scores(HUB_BETWEENNESS_SAMPLES));
+                egui::Grid::new("hub_grid").striped(true).show(ui, |ui| {
+                    ui.strong("System");
+                    ui.strong("Links");
+                    ui.strong("Paths through").on_hover_text("Approximate betweenness centrality");
+                    ui.end_row();
+                    for score in scores.iter().take(HUB_COUNT) {
+                        let node = &star_map.graph[score.node];
+                        if ui.selectable_label(self.selected_star == Some(score.node), &node.name).clicked() {
+                            self.selected_star = Some(score.node);
+                            self.view.center_on(node.position);
+                            self.following_ship = None;
+                        }
+                        ui.label(score.degree.to_string());
+                        ui.label(format!("{:.0}", score.betweenness));
+                        ui.end_row();
+                    }
+                });
+            });
+        
         ui.separator();
+    }
+    
+    fn draw_focus_panel(&mut self, ui: &mut egui::Ui) {
+        let (Some(star_map), Some(center)) = (&self.star_map, self.focus_center) else {
+            return;
+        };
         
-        // Marker visibility
-        ui.label("Show markers:");
-        let mut markers_changed = false;
-        markers_changed |= ui.checkbox(&mut self.show_cx, "🔴 Commodity Exchanges").changed();
-        markers_changed |= ui.checkbox(&mut self.show_bases, "🟢 Bases").changed();
-        markers_changed |= ui.checkbox(&mut self.show_ships, "🔵 Ships").changed();
+        // Allow a radius large enough to cover the whole galaxy
+        let size = star_map.stats.bounds.size();
+        let max_radius = size[0].max(size[1]).max(size[2]).max(10.0);
         
-        if markers_changed {
-            self.update_system_markers();
+        ui.heading("Focus region");
+        ui.label(format!("Center: {}", star_map.graph[center].name));
+        ui.add(egui::Slider::new(&mut self.focus_radius, 1.0..=max_radius).text("Radius"));
+        if ui.button("Clear focus").clicked() {
+            self.focus_center = None;
         }
-
+        
         ui.separator();
-
-        // Zoom controls
-        ui.label(format!("Zoom: {:.2}x", self.view.zoom));
-        ui.horizontal(|ui| {
-            if ui.button("-").clicked() {
-                self.view.zoom = (self.view.zoom * 0.8).max(0.05);
-            }
-            if ui.button("+").clicked() {
-                self.view.zoom = (self.view.zoom * 1.25).min(5.0);
+    }
+    
+    // Rebuild the route from the waypoint list
+    fn update_waypoint_route(&mut self) {
+        let Some(star_map) = &self.star_map else {
+            return;
+        };
+        if self.waypoints.len() < 2 {
+            self.route = None;
+            self.unreachable_route = None;
+            return;
+        }
+        match star_map.route_through(&self.waypoints) {
+            Ok(route) => {
+                self.route = Some(route);
+                self.unreachable_route = None;
             }
-            if ui.button("Reset").clicked() {
-                self.view = MapView::default();
+            Err(leg) => {
+                self.route = None;
+                self.unreachable_route = Some(leg);
             }
-        });
-
+        }
+    }
+    
+    fn draw_waypoint_panel(&mut self, ui: &mut egui::Ui) {
+        let Some(star_map) = &self.star_map else {
+            return;
+        };
+        if self.waypoints.is_empty() {
+            return;
+        }
+        
+        ui.heading("Waypoints");
+        let mut move_up = None;
+        let mut remove = None;
+        let mut center = None;
+        for (i, &idx) in self.waypoints.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.selectable_label(false, format!("{}. {}", i + 1, star_map.graph[idx].name)).clicked() {
+                    center = Some(idx);
+                }
+                if ui.add_enabled(i > 0, egui::Button::new("⏶").small()).clicked() {
+                    move_up = Some(i);
+                }
+                if ui.add_enabled(i + 1 < self.waypoints.len(), egui::Button::new("⏷").small()).clicked() {
+                    move_up = Some(i + 1);
+                }
+                if ui.small_button("✖").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        let clear = ui.button("Clear waypoints").clicked();
+        
+        if let Some(idx) = center {
+            self.view.center_on(star_map.graph[idx].position);
+            self.following_ship = None;
+        }
+        let changed = move_up.is_some() || remove.is_some() || clear;
+        if let Some(i) = move_up {
+            self.waypoints.swap(i - 1, i);
+        }
+        if let Some(i) = remove {
+            self.waypoints.remove(i);
+        }
+        if clear {
+            self.waypoints.clear();
+        }
+        if changed {
+            self.update_waypoint_route();
+        }
+        
         ui.separator();
-
-        // Search
-        ui.label("Search:");
-        ui.text_edit_singleline(&mut self.search_query);
+    }
+    
+    fn draw_route_panel(&mut self, ui: &mut egui::Ui) {
+        if let (Some(star_map), Some((start, end))) = (&self.star_map, self.unreachable_route) {
+            ui.heading("Route");
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                format!("No route from {} to {}", star_map.graph[start].name, star_map.graph[end].name),
+            );
+            if ui.button("Dismiss").clicked() {
+                self.unreachable_route = None;
+            }
+            ui.separator();
+        }
         
-        if !self.search_query.is_empty() {
-            if let Some(star_map) = &self.star_map {
-                let query = self.search_query.to_lowercase();
-                let matches: Vec<_> = star_map.graph.node_indices()
-                    .filter(|&idx| {
-                        let node = &star_map.graph[idx];
-                        node.name.to_lowercase().contains(&query) ||
-                        node.natural_id.to_lowercase().contains(&query)
-                    })
-                    .take(10)
-                    .collect();
-
-                for idx in matches {
-                    let node = &star_map.graph[idx];
-                    if ui.selectable_label(
-                        self.selected_star == Some(idx),
-                        &node.name
-                    ).clicked() {
-                        self.selected_star = Some(idx);
-                        // Center on selected star
-                        let pos = node.position;
-                        let (x, y) = match self.view.projection {
-                            Projection::XY => (pos[0], pos[1]),
-                            Projection::XZ => (pos[0], pos[2]),
-                            Projection::YZ => (pos[1], pos[2]),
-                        };
-                        self.view.offset = egui::vec2(-x * self.view.zoom, -y * self.view.zoom);
+        let (Some(star_map), Some(route)) = (&self.star_map, &self.route) else {
+            return;
+        };
+        let (Some(&start), Some(&end)) = (route.first(), route.last()) else {
+            return;
+        };
+        
+        let start_node = &star_map.graph[start];
+        let end_node = &star_map.graph[end];
+        
+        let jumps = route.len() - 1;
+        let distance: f32 = route.windows(2)
+            .map(|pair| star_map.graph[pair[0]].distance_to(&star_map.graph[pair[1]]))
+            .sum();
+        
+        ui.heading("Route");
+        ui.label(format!("{} → {}", start_node.name, end_node.name));
+        ui.label(format!("Jumps: {}", jumps));
+        ui.label(format!("Systems traversed: {}", route.len()));
+        ui.label(format!("Path length: {:.1} units", distance));
+        ui.label(format!("Straight line: {:.1} units", start_node.distance_to(end_node)))
+            .on_hover_text("Euclidean distance between the route's endpoints");
+        
+        // Travel time estimated from the player's own flights, if any have jump timings
+        let estimate = self.user_data.as_ref()
+            .and_then(|user_data| TravelTimeEstimate::from_flights(&user_data.flights));
+        if let Some(estimate) = estimate {
+            ui.label(format!("Est. travel time: ~{}", format_duration(estimate.route_ms(jumps))))
+                .on_hover_text(format!(
+                    "{} × {} per jump + 2 × {} in-system",
+                    jumps,
+                    format_duration(estimate.jump_ms),
+                    format_duration(estimate.in_system_leg_ms),
+                ));
+        }
+        
+        ui.checkbox(&mut self.dim_off_route_connections, "Dim other connections");
+        
+        let mut clear = false;
+        ui.horizontal(|ui| {
+            if ui.button("Export CSV").clicked() {
+                let csv = export::route_csv(star_map, route);
+                let filename = format!("route_{}_{}.csv", start_node.natural_id, end_node.natural_id);
+                if let Err(e) = export::download_text(&filename, "text/csv", &csv) {
+                    tracing::warn!("Failed to export route: {:?}", e);
+                }
+            }
+            if ui.button("📋 Export route").on_hover_text("Copy the route's system IDs and names to the clipboard").clicked() {
+                // Started within the click so the browser allows the clipboard write
+                let text = export::route_text(star_map, route);
+                self.route_export_fallback = None;
+                match export::copy_to_clipboard(&text) {
+                    Ok(write) => self.route_copy_pending = Some((text, write)),
+                    Err(e) => {
+                        tracing::warn!("Failed to copy route to the clipboard: {:?}", e);
+                        self.route_export_fallback = Some(text);
                     }
                 }
             }
+            if ui.button("Clear route").clicked() {
+                clear = true;
+            }
+        });
+        
+        // The clipboard was unavailable, so let the user copy the text by hand
+        let mut close_fallback = false;
+        if let Some(text) = &self.route_export_fallback {
+            ui.small("Couldn't access the clipboard. Select and copy the route below:");
+            ui.add(egui::TextEdit::multiline(&mut text.as_str()).desired_rows(4).code_editor());
+            close_fallback = ui.small_button("Close").clicked();
         }
-
+        
+        if clear || close_fallback {
+            self.route_export_fallback = None;
+        }
+        if clear {
+            self.route = None;
+            self.waypoints.clear();
+        }
+        
         ui.separator();
-
-        // Selected star info
-        if let Some(selected_idx) = self.selected_star {
-            if let Some(star_map) = &self.star_map {
-                let node = &star_map.graph[selected_idx];
-                ui.heading(&node.name);
-                ui.label(format!("ID: {}", node.natural_id));
-                ui.label(format!("Type: {:?}", node.star_type));
-                ui.label(format!("Position: ({:.1}, {:.1}, {:.1})", 
-                    node.position[0], node.position[1], node.position[2]));
-                ui.label(format!("Sector: {}", node.sector_id));
-                
-                // Show marker info (all markers for this system)
-                if let Some(markers) = self.system_markers.get(&node.natural_id) {
-                    for marker in markers {
-                        let marker_text = match marker {
-                            SystemMarker::CommodityExchange => {
-                                if let Some(cx_name) = self.cx_names.get(&node.natural_id) {
-                                    format!("🔴 CX: {}", cx_name)
-                                } else {
-                                    "🔴 Commodity Exchange".to_string()
-                                }
+    }
+    
+    fn draw_bases_section(&mut self, ui: &mut egui::Ui) {
+        let Some(user_data) = &self.user_data else {
+            return;
+        };
+        
+        let mut clicked_system = None;
+        egui::CollapsingHeader::new(format!("🏠 Bases ({})", user_data.sites.len()))
+            .id_salt("bases_section")
+            .show(ui, |ui| {
+                for site in &user_data.sites {
+                    let planet_id = site.planet_identifier.as_deref().unwrap_or("?");
+                    let name = site.planet_name.as_deref().unwrap_or(planet_id);
+                    ui.horizontal(|ui| {
+                        if ui.selectable_label(false, name).on_hover_text(planet_id).clicked() {
+                            clicked_system = site.planet_identifier.as_deref().map(extract_system_from_planet);
+                        }
+                        if let (Some(invested), Some(maximum)) = (site.invested_permits, site.maximum_permits) {
+                            let text = format!("{}/{} permits", invested, maximum);
+                            if invested >= maximum {
+                                ui.colored_label(egui::Color32::YELLOW, text).on_hover_text("All permits used");
+                            } else {
+                                ui.label(text);
                             }
-                            SystemMarker::Base => "🟢 Your Base".to_string(),
-                            SystemMarker::Ship => "🔵 Your Ship".to_string(),
-                        };
-                        ui.colored_label(marker.color(), marker_text);
+                        }
+                    });
+                }
+            });
+        
+        let Some(star_map) = &self.star_map else {
+            return;
+        };
+        if let Some(&idx) = clicked_system.and_then(|id| star_map.natural_id_to_node.get(&id)) {
+            self.selected_star = Some(idx);
+            self.view.center_on(star_map.graph[idx].position);
+            self.following_ship = None;
+        }
+    }
+    
+    fn draw_ships_section(&mut self, ui: &mut egui::Ui) {
+        let Some(user_data) = &self.user_data else {
+            return;
+        };
+        
+        let reveal = std::mem::take(&mut self.reveal_ship_list);
+        egui::CollapsingHeader::new(format!("🚀 Ships ({})", user_data.ships.len()))
+            .id_salt("ships_section")
+            .open(reveal.then_some(true))
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Sort:");
+                    ui.selectable_value(&mut self.ship_sort, ShipSort::Registration, "Registration");
+                    ui.selectable_value(&mut self.ship_sort, ShipSort::Newest, "Newest");
+                    ui.selectable_value(&mut self.ship_sort, ShipSort::Oldest, "Oldest");
+                });
+                ui.add(egui::Slider::new(&mut self.ship_min_age_days, 0..=1000).text("Min. age (days)"));
+                if let Some(system_id) = &self.ship_system_filter {
+                    let mut clear = false;
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Docked in {}", system_id));
+                        clear = ui.small_button("✖").clicked();
+                    });
+                    if clear {
+                        self.ship_system_filter = None;
                     }
                 }
                 
-                // Show production buttons for bases in this system
-                if let Some(user_data) = &self.user_data {
-                    let system_id = &node.natural_id;
-                    let bases_in_system: Vec<_> = user_data.base_production.iter()
-                        .filter(|b| extract_system_from_planet(&b.planet_natural_id) == *system_id)
-                        .collect();
-                    
-                    if !bases_in_system.is_empty() {
-                        ui.separator();
-                        ui.heading("📊 Production Rates");
-                        
-                        for base in &bases_in_system {
-                            if ui.button(format!("🏭 {}", base.planet_name)).clicked() {
-                                self.production_windows_open.insert(base.planet_natural_id.clone());
+                let now = now_ms();
+                let age_ms = |ship: &Ship| ship.commissioning_time_epoch_ms.map(|t| now - t as f64);
+                
+                let mut ships: Vec<&Ship> = user_data.ships.iter()
+                    .filter(|ship| {
+                        self.ship_min_age_days == 0
+                            || age_ms(*ship).is_some_and(|age| age >= self.ship_min_age_days as f64 * MS_PER_DAY)
+                    })
+                    .filter(|ship| {
+                        self.ship_system_filter.as_ref().is_none_or(|system_id| {
+                            ship.location.as_deref()
+                                .is_some_and(|location| !location.is_empty() && extract_system_from_planet(location) == *system_id)
+                        })
+                    })
+                    .collect();
+                match self.ship_sort {
+                    ShipSort::Registration => ships.sort_by(|a, b| a.registration.cmp(&b.registration)),
+                    ShipSort::Newest => ships.sort_by_key(|ship| std::cmp::Reverse(ship.commissioning_time_epoch_ms)),
+                    ShipSort::Oldest => ships.sort_by_key(|ship| ship.commissioning_time_epoch_ms.unwrap_or(i64::MAX)),
+                }
+                
+                if ships.is_empty() {
+                    ui.label("No matching ships");
+                }
+                
+                // Ships in flight have no location
+                let (docked, flying): (Vec<&Ship>, Vec<&Ship>) = ships.into_iter()
+                    .partition(|ship| ship.location.as_deref().is_some_and(|location| !location.is_empty()));
+                
+                let mut clicked_system = None;
+                let mut clicked_flight = None;
+                for (heading, group) in [("Docked", docked), ("In flight", flying)] {
+                    if group.is_empty() {
+                        continue;
+                    }
+                    ui.strong(format!("{} ({})", heading, group.len()));
+                    for ship in group {
+                        let title = match &ship.name {
+                            Some(name) if !name.is_empty() => format!("{} ({})", name, ship.registration),
+                            _ => ship.registration.clone(),
+                        };
+                        let flight = user_data.flight_paths.iter()
+                            .find(|f| f.ship_id.as_ref() == Some(&ship.ship_id));
+                        ui.horizontal(|ui| {
+                            let hover = match (&ship.location, flight) {
+                                (Some(location), _) if !location.is_empty() => format!("Docked at {}", location),
+                                (_, Some(flight)) => format!("{} → {}", flight.origin_system_id, flight.destination_system_id),
+                                _ => "Location unknown".to_string(),
+                            };
+                            if ui.selectable_label(false, title).on_hover_text(hover).clicked() {
+                                match (&ship.location, flight) {
+                                    (Some(location), _) if !location.is_empty() => {
+                                        clicked_system = Some(extract_system_from_planet(location));
+                                    }
+                                    (_, Some(flight)) => clicked_flight = Some(flight),
+                                    _ => {}
+                                }
+                            }
+                            if let Some(condition) = ship.condition {
+                                ui.label(format!("{:.0}%", condition * 100.0)).on_hover_text("Condition");
+                            }
+                            if ship.is_ftl_capable() {
+                                ui.small("FTL-capable").on_hover_text("Has an FTL fuel store and can jump between systems");
+                            } else {
+                                ui.small("STL only").on_hover_text("No FTL fuel store; limited to its current system");
+                            }
+                            if flight.is_some() && ui.small_button("👁 Follow").clicked() {
+                                self.following_ship = Some(ship.ship_id.clone());
                             }
+                            if let (None, Some(range)) = (flight, ship.ftl_range_estimate()) {
+                                let showing = self.range_ship.as_ref() == Some(&ship.ship_id);
+                                if ui.selectable_label(showing, "◎ Range")
+                                    .on_hover_text(format!("Estimated FTL range: {:.0} units (emitter power ÷ mass)", range))
+                                    .clicked()
+                                {
+                                    self.range_ship = if showing { None } else { Some(ship.ship_id.clone()) };
+                                }
+                            }
+                        });
+                        if let Some(text) = flight.and_then(|flight| arrival_text(flight, now_ms())) {
+                            ui.small(text);
+                        }
+                        if let Some(age) = age_ms(ship) {
+                            ui.small(format!("commissioned {} ago", format_duration(age)));
                         }
                     }
                 }
-
-                // Show connections
-                ui.separator();
-                let neighbors: Vec<_> = star_map.graph.neighbors(selected_idx).collect();
-                if !neighbors.is_empty() {
-                    ui.label(format!("Connections: {}", neighbors.len()));
-                    egui::ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
-                        for neighbor_idx in neighbors {
-                            let neighbor = &star_map.graph[neighbor_idx];
-                            if ui.selectable_label(false, &neighbor.name).clicked() {
-                                self.selected_star = Some(neighbor_idx);
-                            }
-                        }
-                    });
+                
+                // Docked ships jump to their system; ships in flight frame their flight path
+                let Some(star_map) = &self.star_map else {
+                    return;
+                };
+                if let Some(&idx) = clicked_system.and_then(|id| star_map.natural_id_to_node.get(&id)) {
+                    self.selected_star = Some(idx);
+                    self.view.center_on(star_map.graph[idx].position);
+                    self.following_ship = None;
                 }
-            }
-        }
+                if let Some(flight) = clicked_flight {
+                    let ends: Vec<NodeIndex> = [&flight.origin_system_id, &flight.destination_system_id].into_iter()
+                        .filter_map(|id| star_map.natural_id_to_node.get(id).copied())
+                        .collect();
+                    self.view.fit(ends.iter().map(|&idx| star_map.graph[idx].position), self.map_size);
+                    self.selected_star = ends.last().copied();
+                    self.following_ship = None;
+                }
+            });
     }
     
     fn draw_auth_panel(&mut self, ui: &mut egui::Ui) {
         ui.separator();
         ui.heading("FIO Login");
         
+        egui::CollapsingHeader::new("⚙ API server")
+            .id_salt("api_server")
+            .show(ui, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.api_base_url).hint_text(api::DEFAULT_API_BASE),
+                );
+                if response.lost_focus() {
+                    save_api_base_url(&self.api_base_url);
+                }
+                ui.small("Leave blank to use the public FIO API");
+            });
+        
         if self.auth_token.is_some() {
             ui.label(format!("✅ Logged in as: {}", self.username));
             
@@ -596,15 +3838,22 @@ impl StarMapApp {
             } else if let Some(user_data) = &self.user_data {
                 ui.label(format!("Ships: {} systems", user_data.ship_system_ids.len()));
                 ui.label(format!("Bases: {} systems", user_data.base_system_ids.len()));
+                ui.label(format!("Warehouses: {}", user_data.warehouse_locations.len()));
+                for (endpoint, error) in &user_data.load_errors {
+                    ui.colored_label(egui::Color32::YELLOW, format!("⚠ {} failed to load: {}", endpoint, error));
+                }
+                self.draw_ships_section(ui);
+                self.draw_bases_section(ui);
             }
             
             if ui.button("Logout").clicked() {
                 self.auth_token = None;
+                self.auth_expiry_ms = None;
                 self.user_data = None;
                 self.username.clear();
                 self.password.clear();
                 clear_auth();
-                self.update_system_markers();
+                self.markers_dirty = true;
             }
         } else {
             ui.label("Username:");
@@ -619,21 +3868,57 @@ impl StarMapApp {
                 ui.colored_label(egui::Color32::RED, error);
             }
             
-            let can_login = !self.username.is_empty() && !self.password.is_empty() && !self.logging_in;
+            let can_login = !self.username.is_empty() && !self.password.is_empty()
+                && self.login_state == LoginState::Idle;
             
             ui.add_enabled_ui(can_login, |ui| {
                 if ui.button("Login").clicked() {
-                    self.logging_in = true;
+                    self.login_state = LoginState::Requested;
                     self.login_error = None;
                 }
             });
             
-            if self.logging_in {
+            if self.login_state == LoginState::InFlight {
                 ui.spinner();
             }
         }
     }
     
+    fn draw_whats_new_window(&mut self, ctx: &egui::Context) {
+        if !self.show_whats_new {
+            return;
+        }
+        
+        let mut dismissed = false;
+        egui::Window::new(format!("What's new in v{}", APP_VERSION))
+            .collapsible(false)
+            .resizable(true)
+            .default_width(400.0)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for line in RELEASE_NOTES.lines() {
+                        if let Some(heading) = line.strip_prefix("## ") {
+                            ui.strong(heading);
+                        } else if let Some(item) = line.strip_prefix("- ") {
+                            ui.label(format!("• {}", item));
+                        } else if !line.is_empty() && !line.starts_with("# ") {
+                            ui.label(line);
+                        }
+                    }
+                });
+                ui.separator();
+                if ui.button("Got it").clicked() {
+                    dismissed = true;
+                }
+            });
+        
+        if dismissed {
+            self.show_whats_new = false;
+            save_last_seen_version();
+        }
+    }
+    
     fn draw_production_window(&mut self, ctx: &egui::Context) {
         if self.production_windows_open.is_empty() {
             return;
@@ -721,6 +4006,62 @@ impl StarMapApp {
 
 impl eframe::App for StarMapApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if std::mem::take(&mut self.markers_dirty) {
+            self.refresh_system_markers();
+        }
+        self.update_jump_range_counts();
+        self.update_nearest_exchanges();
+        
+        // Keyboard shortcuts (ignored while typing in a text field)
+        if !ctx.wants_keyboard_input() {
+            if ctx.input(|i| i.key_pressed(egui::Key::P)) {
+                self.view.projection = self.view.projection.next();
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::Plus) || i.key_pressed(egui::Key::Equals)) {
+                self.view.zoom_by(1.25, egui::Vec2::ZERO);
+            }
+            if ctx.input(|i| i.key_pressed(egui::Key::Minus)) {
+                self.view.zoom_by(0.8, egui::Vec2::ZERO);
+            }
+            
+            // Hop between connected systems. Without a selection the keys are left alone so Tab
+            // still moves focus between widgets.
+            let neighbor_keys = if self.selected_star.is_some() {
+                vec![
+                    (egui::Key::ArrowRight, NeighborTarget::Direction(egui::Vec2::RIGHT)),
+                    (egui::Key::ArrowLeft, NeighborTarget::Direction(egui::Vec2::LEFT)),
+                    (egui::Key::ArrowUp, NeighborTarget::Direction(egui::Vec2::UP)),
+                    (egui::Key::ArrowDown, NeighborTarget::Direction(egui::Vec2::DOWN)),
+                    (egui::Key::Tab, NeighborTarget::Next),
+                ]
+            } else {
+                Vec::new()
+            };
+            for (key, target) in neighbor_keys {
+                if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key)) {
+                    self.select_neighbor(target);
+                }
+            }
+            
+            // Pan while WASD is held, or the arrow keys when there's no selection to hop from
+            let arrows_pan = self.selected_star.is_none();
+            let pan = ctx.input(|i| {
+                let held = |letter: egui::Key, arrow: egui::Key| i.key_down(letter) || (arrows_pan && i.key_down(arrow));
+                let mut direction = egui::Vec2::ZERO;
+                if held(egui::Key::W, egui::Key::ArrowUp) { direction.y += 1.0; }
+                if held(egui::Key::S, egui::Key::ArrowDown) { direction.y -= 1.0; }
+                if held(egui::Key::A, egui::Key::ArrowLeft) { direction.x += 1.0; }
+                if held(egui::Key::D, egui::Key::ArrowRight) { direction.x -= 1.0; }
+                direction * KEYBOARD_PAN_SPEED * i.stable_dt
+            });
+            if pan != egui::Vec2::ZERO {
+                self.view.offset += pan;
+                self.view.fly_target = None;
+                self.following_ship = None;
+                ctx.request_repaint();
+            }
+        }
+
         // Side panel
         egui::SidePanel::left("controls")
             .min_width(200.0)
@@ -738,9 +4079,28 @@ impl eframe::App for StarMapApp {
 
         // Production window (pop-out)
         self.draw_production_window(ctx);
+        
+        self.draw_whats_new_window(ctx);
+        
+        self.persist_selection();
+        self.persist_view_state(ctx);
+        self.handle_screenshot(ctx);
 
         // Request repaint for smooth interaction
-        if self.hovered_star.is_some() || self.loading || self.logging_in || self.loading_user_data {
+        if self.hovered_star.is_some() || self.loading || self.login_state != LoginState::Idle || self.loading_user_data
+            || self.loading_resources || self.view.is_transitioning() || self.view.is_zooming()
+            || self.following_ship.is_some() || self.markers_dirty
+        {
+            ctx.request_repaint();
+        }
+        
+        // Keep animating ships that are still on their way
+        let now = now_ms();
+        let ships_in_flight = self.user_data.as_ref().is_some_and(|user_data| {
+            user_data.flight_paths.iter()
+                .any(|flight| !flight.is_in_system && flight.progress(now).is_some_and(|t| t < 1.0))
+        });
+        if ships_in_flight {
             ctx.request_repaint();
         }
     }
@@ -783,63 +4143,127 @@ pub async fn start() -> Result<(), JsValue> {
 
 // Message types for async operations
 enum AppMessage {
-    StarSystemsLoaded(Result<Vec<data::StarSystem>, String>),
-    ExchangeStationsLoaded(Result<Vec<data::ExchangeStation>, String>),
-    LoginResult(Result<(String, String), String>), // (auth_token, username)
-    UserDataLoaded(Result<UserData, String>),
+    StarSystemsLoaded(Result<(Vec<data::StarSystem>, usize), ApiError>), // (systems, skipped records)
+    StarSystemsImported(Result<(Vec<data::StarSystem>, usize), String>), // From a file picked by the user
+    StarSystemsProgress(api::DownloadProgress), // Bytes of the star systems download received so far
+    ClipboardWriteFailed(String), // The text that couldn't be copied
+    ExchangeStationsLoaded(Result<Vec<data::ExchangeStation>, ApiError>),
+    LoginResult(Result<(data::AuthResponse, String), ApiError>), // (auth response, username)
+    AuthRefreshed(String, Result<data::AuthResponse, ApiError>), // (token that was refreshed, result)
+    UserDataLoaded(Result<UserData, ApiError>),
+    PlanetResourcesLoaded(Result<HashMap<String, SystemResources>, ApiError>),
+    SystemPlanetsLoaded(String, Result<Vec<Planet>, ApiError>), // (system natural_id, planets)
+    ExchangePricesLoaded(Result<Vec<ExchangePrice>, ApiError>), // All exchanges
 }
 
 const MS_PER_DAY: f64 = 86_400_000.0;
 
-/// Fetch all user data (ships, flights, bases, production) from the API
-async fn fetch_all_user_data(username: &str, auth_token: &str) -> UserData {
+// Auth failures abort the whole user data load so the session can be expired; other
+// errors only drop that endpoint's data and are recorded so they can be shown
+fn fail_on_auth<T>(
+    endpoint: &'static str,
+    result: Result<T, ApiError>,
+    errors: &mut Vec<(&'static str, String)>,
+) -> Result<Option<T>, ApiError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(ApiError::Auth) => Err(ApiError::Auth),
+        Err(e) => {
+            tracing::warn!("Failed to load {} for the user data: {}", endpoint, e);
+            errors.push((endpoint, e.to_string()));
+            Ok(None)
+        }
+    }
+}
+
+/// Fetch all user data (ships, flights, bases, production) from the API.
+/// Fails with [`ApiError::Auth`] if the token was rejected.
+async fn fetch_all_user_data(config: &api::ApiConfig, username: &str, auth_token: &str) -> Result<UserData, ApiError> {
     let mut user_data = UserData {
         username: username.to_string(),
+        ships: Vec::new(),
+        sites: Vec::new(),
         ship_system_ids: HashSet::new(),
+        ftl_ship_system_ids: HashSet::new(),
         base_system_ids: HashSet::new(),
+        warehouse_locations: HashSet::new(),
+        flights: Vec::new(),
         flight_paths: Vec::new(),
         base_production: Vec::new(),
+        load_errors: Vec::new(),
     };
     
-    // Fetch ships (docked only - ships in flight have empty location)
-    if let Ok(ships) = api::fetch_ships(username, auth_token).await {
-        for ship in ships {
-            if let Some(location) = ship.location {
+    // The endpoints are independent, so wait for them together rather than one after another
+    let (ships, flights, sites, warehouses, production_lines) = futures::join!(
+        api::fetch_ships(config, username, auth_token),
+        api::fetch_flights(config, username, auth_token),
+        api::fetch_sites(config, username, auth_token),
+        api::fetch_warehouses(config, username, auth_token),
+        api::fetch_production(config, username, auth_token),
+    );
+    
+    // Ships (docked only - ships in flight have empty location)
+    if let Some(ships) = fail_on_auth("Ships", ships, &mut user_data.load_errors)? {
+        for ship in &ships {
+            if let Some(location) = &ship.location {
                 if !location.is_empty() {
-                    user_data.ship_system_ids.insert(extract_system_from_planet(&location));
+                    let system_id = extract_system_from_planet(location);
+                    if ship.is_ftl_capable() {
+                        user_data.ftl_ship_system_ids.insert(system_id.clone());
+                    }
+                    user_data.ship_system_ids.insert(system_id);
                 }
             }
         }
+        user_data.ships = ships;
     }
     
-    // Fetch active flights
-    if let Ok(flights) = api::fetch_flights(username, auth_token).await {
-        for flight in flights {
+    // Active flights
+    if let Some(flights) = fail_on_auth("Flights", flights, &mut user_data.load_errors)? {
+        for flight in &flights {
             if let (Some(origin), Some(dest)) = (
                 flight.origin_system_natural_id(),
                 flight.destination_system_natural_id(),
             ) {
+                let ship_registration = user_data.ships.iter()
+                    .find(|ship| Some(&ship.ship_id) == flight.ship_id.as_ref())
+                    .map(|ship| ship.registration.clone())
+                    .or_else(|| flight.ship_id.clone());
                 user_data.flight_paths.push(FlightPath {
                     origin_system_id: origin.clone(),
                     destination_system_id: dest.clone(),
-                    ship_registration: flight.ship_id,
+                    ship_id: flight.ship_id.clone(),
+                    ship_registration,
+                    departure_time_epoch_ms: flight.departure_time_epoch_ms,
+                    arrival_time_epoch_ms: flight.arrival_time_epoch_ms,
                     is_in_system: origin == dest,
                 });
             }
         }
+        user_data.flights = flights;
     }
     
-    // Fetch bases/sites
-    if let Ok(sites) = api::fetch_sites(username, auth_token).await {
-        for site in sites {
-            if let Some(planet_id) = site.planet_identifier {
-                user_data.base_system_ids.insert(extract_system_from_planet(&planet_id));
+    // Bases/sites
+    if let Some(mut sites) = fail_on_auth("Bases", sites, &mut user_data.load_errors)? {
+        for site in &sites {
+            if let Some(planet_id) = &site.planet_identifier {
+                user_data.base_system_ids.insert(extract_system_from_planet(planet_id));
             }
         }
+        sites.sort_by(|a, b| a.planet_name.cmp(&b.planet_name));
+        user_data.sites = sites;
+    }
+    
+    // Warehouses
+    if let Some(warehouses) = fail_on_auth("Warehouses", warehouses, &mut user_data.load_errors)? {
+        user_data.warehouse_locations = warehouses.into_iter()
+            .filter_map(|w| w.location_natural_id)
+            .filter(|location| !location.is_empty())
+            .collect();
     }
     
-    // Fetch production data and calculate daily rates
-    if let Ok(production_lines) = api::fetch_production(username, auth_token).await {
+    // Production data, converted to daily rates
+    if let Some(production_lines) = fail_on_auth("Production", production_lines, &mut user_data.load_errors)? {
         // Group by planet and calculate rates
         let mut planet_rates: HashMap<String, BaseProduction> = HashMap::new();
         
@@ -939,7 +4363,122 @@ async fn fetch_all_user_data(username: &str, auth_token: &str) -> UserData {
         user_data.base_production.sort_by(|a, b| a.planet_name.cmp(&b.planet_name));
     }
     
-    user_data
+    Ok(user_data)
+}
+
+#[derive(Serialize, Deserialize)]
+struct StarSystemsCache {
+    saved_at_ms: f64,
+    #[serde(default)]
+    base_url: String, // Server the systems came from; caches from another server are ignored
+    systems: Vec<data::StarSystem>,
+}
+
+/// Fetch star systems, using the copy cached in localStorage while it is fresh and from
+/// the same server
+async fn fetch_star_systems_cached(
+    config: &api::ApiConfig,
+    bypass_cache: bool,
+    on_progress: impl Fn(api::DownloadProgress),
+) -> Result<(Vec<data::StarSystem>, usize), ApiError> {
+    let storage = get_local_storage();
+    
+    if !bypass_cache {
+        let cached = storage.as_ref()
+            .and_then(|storage| storage.get_item(STAR_SYSTEMS_CACHE_KEY).ok()?)
+            .and_then(|json| serde_json::from_str::<StarSystemsCache>(&json).ok())
+            .filter(|cache| cache.base_url == config.base_url && now_ms() - cache.saved_at_ms < STAR_SYSTEMS_CACHE_TTL_MS);
+        if let Some(cache) = cached {
+            return Ok((cache.systems, 0));
+        }
+    }
+    
+    let (systems, skipped) = api::fetch_star_systems(config, on_progress).await?;
+    
+    let cache = StarSystemsCache { saved_at_ms: now_ms(), base_url: config.base_url.clone(), systems };
+    if let (Some(storage), Ok(json)) = (&storage, serde_json::to_string(&cache)) {
+        // Usually fails because the storage quota is exceeded; the cache is just skipped then
+        if let Err(e) = storage.set_item(STAR_SYSTEMS_CACHE_KEY, &json) {
+            tracing::warn!("Could not cache star systems: {:?}", e);
+        }
+    }
+    
+    Ok((cache.systems, skipped))
+}
+
+/// Fetch all planets and materials and aggregate each system's resources
+async fn fetch_planet_resources(config: &api::ApiConfig) -> Result<HashMap<String, SystemResources>, ApiError> {
+    let materials = api::fetch_materials(config).await?;
+    let planets = api::fetch_planets_full(config).await?;
+    
+    let tickers: HashMap<String, String> = materials.into_iter()
+        .filter_map(|m| Some((m.material_id?, m.ticker?)))
+        .collect();
+    
+    let mut systems: HashMap<String, SystemResources> = HashMap::new();
+    for planet in planets {
+        let Some(natural_id) = planet.planet_natural_id else {
+            continue;
+        };
+        let planet_name = planet.planet_name.unwrap_or_else(|| natural_id.clone());
+        let system = systems.entry(extract_system_from_planet(&natural_id)).or_default();
+        
+        for resource in planet.resources.unwrap_or_default() {
+            let Some(ticker) = resource.material_id.as_ref().and_then(|id| tickers.get(id)) else {
+                continue;
+            };
+            system.entry(ticker.clone()).or_default().push(ResourceDeposit {
+                planet_name: planet_name.clone(),
+                factor: resource.factor.unwrap_or(0.0),
+            });
+        }
+    }
+    systems.retain(|_, resources| !resources.is_empty());
+    
+    Ok(systems)
+}
+
+// Maximum number of per-system detail requests in flight at once
+const MAX_CONCURRENT_DETAIL_FETCHES: usize = 4;
+
+// Bounded queue for per-system detail fetches so selecting many systems at once
+// doesn't flood the API
+struct DetailFetchQueue {
+    pending: VecDeque<String>,
+    in_flight: usize,
+    max_in_flight: usize,
+}
+
+impl DetailFetchQueue {
+    fn new(max_in_flight: usize) -> Self {
+        DetailFetchQueue {
+            pending: VecDeque::new(),
+            in_flight: 0,
+            max_in_flight,
+        }
+    }
+    
+    fn push(&mut self, system_id: String) {
+        self.pending.push_back(system_id);
+    }
+    
+    // Take the next system to fetch, if there is a free slot
+    fn start_next(&mut self) -> Option<String> {
+        if self.in_flight >= self.max_in_flight {
+            return None;
+        }
+        let system_id = self.pending.pop_front()?;
+        self.in_flight += 1;
+        Some(system_id)
+    }
+    
+    fn finish(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+    }
+    
+    fn is_busy(&self) -> bool {
+        self.in_flight > 0 || !self.pending.is_empty()
+    }
 }
 
 // Wrapper to handle async data loading
@@ -947,54 +4486,61 @@ struct AppWrapper {
     app: StarMapApp,
     message_receiver: std::sync::mpsc::Receiver<AppMessage>,
     message_sender: std::sync::mpsc::Sender<AppMessage>,
+    detail_queue: DetailFetchQueue,
 }
 
 impl AppWrapper {
     fn new(mut app: StarMapApp) -> Self {
-        app.loading = true;
+        // Star systems are fetched on the first frame
+        app.star_systems_requested = true;
         
-        let (tx, rx) = std::sync::mpsc::channel();
+        app.linked_selection = load_system_from_url();
         
-        // Fetch star systems
-        let tx_stars = tx.clone();
-        wasm_bindgen_futures::spawn_local(async move {
-            let result = api::fetch_star_systems().await;
-            let _ = tx_stars.send(AppMessage::StarSystemsLoaded(result));
-        });
+        let (tx, rx) = std::sync::mpsc::channel();
         
         // Fetch exchange stations (public endpoint)
         let tx_cx = tx.clone();
+        let config = app.api_config();
         wasm_bindgen_futures::spawn_local(async move {
-            let result = api::fetch_exchange_stations().await;
+            let result = api::fetch_exchange_stations(&config).await;
             let _ = tx_cx.send(AppMessage::ExchangeStationsLoaded(result));
         });
         
         // Try to restore auth from localStorage
-        if let Some((auth_token, username)) = load_auth() {
+        if let Some((auth_token, username, expiry)) = load_auth() {
             app.auth_token = Some(auth_token.clone());
+            app.auth_expiry_ms = expiry.as_deref().and_then(parse_expiry);
             app.username = username.clone();
-            app.loading_user_data = true;
             
-            let tx_user = tx.clone();
-            wasm_bindgen_futures::spawn_local(async move {
-                let user_data = fetch_all_user_data(&username, &auth_token).await;
-                let _ = tx_user.send(AppMessage::UserDataLoaded(Ok(user_data)));
-            });
+            // Don't bother fetching with a token we already know has expired
+            if app.auth_expiry_ms.is_some_and(|expiry| now_ms() >= expiry) {
+                app.expire_session();
+            } else {
+                let tx_user = tx.clone();
+                let config = app.api_config();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let result = fetch_all_user_data(&config, &username, &auth_token).await;
+                    let _ = tx_user.send(AppMessage::UserDataLoaded(result));
+                });
+                app.loading_user_data = true;
+            }
         }
         
         Self {
             app,
             message_receiver: rx,
             message_sender: tx,
+            detail_queue: DetailFetchQueue::new(MAX_CONCURRENT_DETAIL_FETCHES),
         }
     }
     
     fn handle_login(&self, username: String, password: String) {
         let tx = self.message_sender.clone();
+        let config = self.app.api_config();
         wasm_bindgen_futures::spawn_local(async move {
-            match api::login(&username, &password).await {
+            match api::login(&config, &username, &password).await {
                 Ok(auth_response) => {
-                    let _ = tx.send(AppMessage::LoginResult(Ok((auth_response.auth_token, username))));
+                    let _ = tx.send(AppMessage::LoginResult(Ok((auth_response, username))));
                 }
                 Err(e) => {
                     let _ = tx.send(AppMessage::LoginResult(Err(e)));
@@ -1003,110 +4549,630 @@ impl AppWrapper {
         });
     }
     
+    fn refresh_auth(&self, auth_token: String) {
+        let tx = self.message_sender.clone();
+        let config = self.app.api_config();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = api::refresh_auth_token(&config, &auth_token).await;
+            let _ = tx.send(AppMessage::AuthRefreshed(auth_token, result));
+        });
+    }
+    
+    // Proactively renew the token shortly before it expires so long sessions never
+    // run into an expired token mid-session
+    fn check_auth_expiry(&mut self) {
+        let (Some(auth_token), Some(expiry_ms)) = (&self.app.auth_token, self.app.auth_expiry_ms) else {
+            return;
+        };
+        
+        let now = now_ms();
+        if now >= expiry_ms && !self.app.refreshing_auth {
+            // Too late to renew; the password isn't stored, so ask the user to log in again
+            self.app.expire_session();
+            return;
+        }
+        
+        if self.app.refreshing_auth || now < self.app.next_auth_refresh_ms {
+            return;
+        }
+        
+        if now >= expiry_ms - AUTH_REFRESH_MARGIN_MS {
+            self.app.refreshing_auth = true;
+            self.refresh_auth(auth_token.clone());
+        }
+    }
+    
+    // Route a finished background task's result to its handler
+    fn handle_message(&mut self, msg: AppMessage) {
+        match msg {
+            AppMessage::StarSystemsLoaded(result) => self.on_star_systems_loaded(result),
+            AppMessage::StarSystemsImported(result) => self.on_star_systems_imported(result),
+            AppMessage::StarSystemsProgress(progress) => {
+                if self.app.loading {
+                    self.app.star_download = Some(progress);
+                }
+            }
+            AppMessage::ClipboardWriteFailed(text) => self.app.route_export_fallback = Some(text),
+            AppMessage::ExchangeStationsLoaded(result) => self.on_exchange_stations_loaded(result),
+            AppMessage::LoginResult(result) => self.on_login_result(result),
+            AppMessage::AuthRefreshed(sent_token, result) => self.on_auth_refreshed(sent_token, result),
+            AppMessage::UserDataLoaded(result) => self.on_user_data_loaded(result),
+            AppMessage::PlanetResourcesLoaded(result) => self.on_planet_resources_loaded(result),
+            AppMessage::SystemPlanetsLoaded(system_id, result) => self.on_system_planets_loaded(system_id, result),
+            AppMessage::ExchangePricesLoaded(result) => self.on_exchange_prices_loaded(result),
+        }
+    }
+    
+    fn on_star_systems_loaded(&mut self, result: Result<(Vec<data::StarSystem>, usize), ApiError>) {
+        self.app.loading = false;
+        if self.app.offline_star_data {
+            tracing::info!("Ignoring fetched star systems, using the loaded file");
+            return;
+        }
+        match result {
+            Ok((systems, skipped)) => {
+                tracing::info!("Loaded {} star systems", systems.len());
+                self.app.set_star_systems(systems, skipped);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load star systems: {}", e);
+                self.app.error = Some(e.to_string());
+            }
+        }
+    }
+    
+    fn on_star_systems_imported(&mut self, result: Result<(Vec<data::StarSystem>, usize), String>) {
+        match result {
+            Ok((systems, skipped)) => {
+                tracing::info!("Imported {} star systems from a file", systems.len());
+                self.app.offline_star_data = true;
+                self.app.loading = false;
+                self.app.error = None;
+                self.app.set_star_systems(systems, skipped);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to import star systems: {}", e);
+                self.app.import_error = Some(e);
+            }
+        }
+    }
+    
+    fn on_exchange_stations_loaded(&mut self, result: Result<Vec<data::ExchangeStation>, ApiError>) {
+        match result {
+            Ok(stations) => {
+                tracing::info!("Loaded {} exchange stations", stations.len());
+                for station in stations {
+                    // Use SystemNaturalId to match with star map
+                    self.app.cx_system_ids.insert(station.system_natural_id.clone());
+                    self.app.station_systems.insert(station.natural_id, station.system_natural_id.clone());
+                    self.app.cx_names.insert(station.system_natural_id, station.comex_code);
+                }
+                self.app.markers_dirty = true;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load exchange stations: {}", e);
+            }
+        }
+    }
+    
+    fn on_login_result(&mut self, result: Result<(data::AuthResponse, String), ApiError>) {
+        self.app.login_state = LoginState::Idle;
+        match result {
+            Ok((auth_response, username)) => {
+                tracing::info!("Logged in as {}", username);
+                let auth_token = auth_response.auth_token;
+                let expiry = auth_response.expiry;
+                
+                // Save to localStorage
+                save_auth(&auth_token, &username, expiry.as_deref());
+                
+                self.app.auth_token = Some(auth_token.clone());
+                self.app.auth_expiry_ms = expiry.as_deref().and_then(parse_expiry);
+                self.app.next_auth_refresh_ms = 0.0;
+                self.app.username = username.clone();
+                self.app.password.clear();
+                self.app.login_error = None;
+                self.app.loading_user_data = true;
+                
+                // Fetch user data
+                self.fetch_user_data(username, auth_token);
+            }
+            Err(ApiError::Auth) => {
+                tracing::warn!("Login rejected");
+                self.app.login_error = Some("Login failed: invalid username or password".to_string());
+            }
+            Err(e) => {
+                tracing::warn!("Login failed: {}", e);
+                self.app.login_error = Some(format!("Login failed: {}", e));
+            }
+        }
+    }
+    
+    fn on_auth_refreshed(&mut self, sent_token: String, result: Result<data::AuthResponse, ApiError>) {
+        self.app.refreshing_auth = false;
+        // Ignore refreshes for a session that has since ended or been replaced by a new login
+        if self.app.auth_token.as_deref() != Some(sent_token.as_str()) {
+            tracing::info!("Ignoring auth refresh for a previous session");
+            return;
+        }
+        match result {
+            Ok(auth_response) => {
+                tracing::info!("Refreshed auth token");
+                save_auth(&auth_response.auth_token, &self.app.username, auth_response.expiry.as_deref());
+                self.app.auth_token = Some(auth_response.auth_token);
+                self.app.auth_expiry_ms = auth_response.expiry.as_deref().and_then(parse_expiry);
+            }
+            Err(ApiError::Auth) => {
+                // The token was rejected outright, so retrying won't help
+                tracing::warn!("Auth token refresh was rejected");
+                self.app.expire_session();
+            }
+            Err(e) => {
+                tracing::warn!("Failed to refresh auth token: {}", e);
+                self.app.next_auth_refresh_ms = now_ms() + AUTH_REFRESH_RETRY_MS;
+            }
+        }
+    }
+    
+    fn on_user_data_loaded(&mut self, result: Result<UserData, ApiError>) {
+        self.app.loading_user_data = false;
+        match result {
+            Ok(user_data) => {
+                tracing::info!(
+                    "Loaded user data: {} ships, {} bases, {} flights, {} endpoints failed",
+                    user_data.ships.len(),
+                    user_data.sites.len(),
+                    user_data.flight_paths.len(),
+                    user_data.load_errors.len(),
+                );
+                self.app.user_data = Some(user_data);
+                self.app.markers_dirty = true;
+            }
+            Err(ApiError::Auth) => {
+                tracing::warn!("User data request was rejected, expiring session");
+                self.app.expire_session();
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load user data: {}", e);
+            }
+        }
+    }
+    
+    fn on_planet_resources_loaded(&mut self, result: Result<HashMap<String, SystemResources>, ApiError>) {
+        self.app.loading_resources = false;
+        match result {
+            Ok(system_resources) => {
+                let mut tickers: Vec<String> = system_resources.values()
+                    .flat_map(|resources| resources.keys().cloned())
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .collect();
+                tickers.sort();
+                tracing::info!("Loaded resources for {} systems, {} materials", system_resources.len(), tickers.len());
+                self.app.resource_tickers = tickers;
+                self.app.system_resources = system_resources;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load planet resources: {}", e);
+                self.app.resource_error = Some(e.to_string());
+            }
+        }
+    }
+    
+    fn on_exchange_prices_loaded(&mut self, result: Result<Vec<ExchangePrice>, ApiError>) {
+        self.app.loading_exchange_prices = false;
+        match result {
+            Ok(prices) => {
+                tracing::info!("Loaded {} exchange prices", prices.len());
+                let mut by_exchange: HashMap<String, Vec<ExchangePrice>> = HashMap::new();
+                for price in prices {
+                    by_exchange.entry(price.exchange_code.clone()).or_default().push(price);
+                }
+                for prices in by_exchange.values_mut() {
+                    prices.sort_by(|a, b| a.material_ticker.cmp(&b.material_ticker));
+                }
+                self.app.exchange_prices = Some(by_exchange);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load exchange prices: {}", e);
+                self.app.exchange_prices_error = Some(e.to_string());
+            }
+        }
+    }
+    
+    fn on_system_planets_loaded(&mut self, system_id: String, result: Result<Vec<Planet>, ApiError>) {
+        self.detail_queue.finish();
+        match result {
+            Ok(planets) => {
+                tracing::info!("Loaded {} planets for {}", planets.len(), system_id);
+                self.app.planet_errors.remove(&system_id);
+                self.app.system_planets.insert(system_id, planets);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load planets for {}: {}", system_id, e);
+                self.app.planet_errors.insert(system_id, e.to_string());
+            }
+        }
+    }
+    
+    // Wait for a clipboard write started by the UI, offering the text for manual copying if it fails
+    fn await_route_copy(&self, text: String, write: wasm_bindgen_futures::JsFuture) {
+        let tx = self.message_sender.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Err(e) = write.await {
+                tracing::warn!("Failed to copy route to the clipboard: {:?}", e);
+                let _ = tx.send(AppMessage::ClipboardWriteFailed(text));
+            }
+        });
+    }
+    
+    fn import_star_systems(&self) {
+        let tx = self.message_sender.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = match import::pick_text_file(".json,application/json").await {
+                Ok(Some(json)) => import::parse_star_systems(&json),
+                Ok(None) => return,
+                Err(e) => Err(format!("Could not read the file: {:?}", e)),
+            };
+            let _ = tx.send(AppMessage::StarSystemsImported(result));
+        });
+    }
+    
+    fn fetch_star_systems(&self, bypass_cache: bool) {
+        let tx = self.message_sender.clone();
+        let config = self.app.api_config();
+        wasm_bindgen_futures::spawn_local(async move {
+            let progress_tx = tx.clone();
+            let result = fetch_star_systems_cached(&config, bypass_cache, move |progress| {
+                let _ = progress_tx.send(AppMessage::StarSystemsProgress(progress));
+            }).await;
+            let _ = tx.send(AppMessage::StarSystemsLoaded(result));
+        });
+    }
+    
+    fn fetch_planet_resources(&self) {
+        let tx = self.message_sender.clone();
+        let config = self.app.api_config();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = fetch_planet_resources(&config).await;
+            let _ = tx.send(AppMessage::PlanetResourcesLoaded(result));
+        });
+    }
+    
+    fn fetch_exchange_prices(&self) {
+        let tx = self.message_sender.clone();
+        let config = self.app.api_config();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = api::fetch_exchange_prices(&config).await;
+            let _ = tx.send(AppMessage::ExchangePricesLoaded(result));
+        });
+    }
+    
+    fn fetch_system_planets(&self, system_id: String) {
+        let tx = self.message_sender.clone();
+        let config = self.app.api_config();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = api::fetch_system_planets(&config, &system_id).await;
+            let _ = tx.send(AppMessage::SystemPlanetsLoaded(system_id, result));
+        });
+    }
+    
     fn fetch_user_data(&self, username: String, auth_token: String) {
         let tx = self.message_sender.clone();
+        let config = self.app.api_config();
         wasm_bindgen_futures::spawn_local(async move {
-            let user_data = fetch_all_user_data(&username, &auth_token).await;
-            let _ = tx.send(AppMessage::UserDataLoaded(Ok(user_data)));
+            let result = fetch_all_user_data(&config, &username, &auth_token).await;
+            let _ = tx.send(AppMessage::UserDataLoaded(result));
         });
     }
 }
 
-// Extract system ID from planet identifier (e.g., "UV-351a" -> "UV-351")
-fn extract_system_from_planet(planet_id: &str) -> String {
-    // Planet IDs typically end with a lowercase letter (a, b, c, etc.)
-    // System IDs are the part before that
-    let chars: Vec<char> = planet_id.chars().collect();
-    if let Some(last) = chars.last() {
-        if last.is_ascii_lowercase() {
-            return chars[..chars.len()-1].iter().collect();
+// A circle outline broken into evenly spaced dashes
+fn dashed_circle(center: egui::Pos2, radius: f32, stroke: egui::Stroke, dashes: usize) -> Vec<egui::Shape> {
+    const SEGMENTS: usize = 48;
+    let points: Vec<egui::Pos2> = (0..=SEGMENTS)
+        .map(|i| center + egui::Vec2::angled(i as f32 / SEGMENTS as f32 * std::f32::consts::TAU) * radius)
+        .collect();
+    let dash = std::f32::consts::PI * radius / dashes as f32;
+    egui::Shape::dashed_line(&points, stroke, dash, dash)
+}
+
+// Round a length to 1, 2 or 5 times a power of ten, down or (with `up`) up.
+// Returns the rounded length and the power of ten.
+fn round_to_nice_step(target: f32, up: bool) -> (f32, i32) {
+    let exponent = target.log10().floor() as i32;
+    let magnitude = 10f32.powi(exponent);
+    let length = if up {
+        [1.0, 2.0, 5.0, 10.0].into_iter()
+            .map(|step| step * magnitude)
+            .find(|&length| length >= target)
+            .unwrap_or(magnitude * 10.0)
+    } else {
+        [5.0, 2.0, 1.0].into_iter()
+            .map(|step| step * magnitude)
+            .find(|&length| length <= target)
+            .unwrap_or(magnitude)
+    };
+    (length, exponent)
+}
+
+// Screen distance from a point to the segment between a and b
+fn distance_to_segment(point: egui::Pos2, a: egui::Pos2, b: egui::Pos2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_sq() > 0.0 {
+        ((point - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(a + ab * t)
+}
+
+// Convex hull of a set of screen points (Andrew's monotone chain), counter-clockwise
+fn convex_hull(points: &[egui::Pos2]) -> Vec<egui::Pos2> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    
+    let cross = |o: egui::Pos2, a: egui::Pos2, b: egui::Pos2| (a - o).x * (b - o).y - (a - o).y * (b - o).x;
+    let mut hull: Vec<egui::Pos2> = Vec::with_capacity(sorted.len() * 2);
+    for pass in [sorted.clone(), sorted.into_iter().rev().collect()] {
+        let start = hull.len();
+        for point in pass {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(point);
         }
+        // The last point of each pass is the first point of the next
+        hull.pop();
+    }
+    hull
+}
+
+// Stable color for a sector: an FNV-1a hash of its ID spread around the hue circle
+fn sector_color(sector_id: &str) -> egui::Color32 {
+    let hash = sector_id.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    // Golden-ratio stepping keeps neighbouring hash values far apart in hue
+    let hue = ((hash % 1024) as f32 * 0.618_034).fract();
+    egui::ecolor::Hsva::new(hue, 0.65, 1.0, 1.0).into()
+}
+
+// Extract system ID from planet identifier (e.g., "UV-351a" -> "UV-351", "OT-580ba" -> "OT-580").
+// Planet suffixes are a run of lowercase letters, optionally followed by moon digits.
+// Anything that doesn't look like a suffixed system ID is returned unchanged.
+fn extract_system_from_planet(planet_id: &str) -> String {
+    let without_moon = planet_id.trim_end_matches(|c: char| c.is_ascii_digit());
+    let without_planet = without_moon.trim_end_matches(|c: char| c.is_ascii_lowercase());
+    
+    // System IDs end in digits, so a real suffix must have been stripped down to one
+    let stripped_suffix = without_planet.len() < without_moon.len();
+    if stripped_suffix && without_planet.ends_with(|c: char| c.is_ascii_digit()) {
+        without_planet.to_string()
+    } else {
+        planet_id.to_string()
     }
-    planet_id.to_string()
 }
 
 impl eframe::App for AppWrapper {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         // Process all pending messages
         while let Ok(msg) = self.message_receiver.try_recv() {
-            match msg {
-                AppMessage::StarSystemsLoaded(result) => {
-                    match result {
-                        Ok(systems) => {
-                            self.app.star_map = Some(Arc::new(StarMap::from_systems(systems)));
-                            self.app.loading = false;
-                            self.app.update_system_markers();
-                        }
-                        Err(e) => {
-                            self.app.error = Some(e);
-                            self.app.loading = false;
-                        }
-                    }
-                }
-                AppMessage::ExchangeStationsLoaded(result) => {
-                    match result {
-                        Ok(stations) => {
-                            for station in stations {
-                                // Use SystemNaturalId to match with star map
-                                self.app.cx_system_ids.insert(station.system_natural_id.clone());
-                                self.app.cx_names.insert(station.system_natural_id, station.comex_code);
-                            }
-                            self.app.update_system_markers();
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to load exchange stations: {}", e);
-                        }
-                    }
-                }
-                AppMessage::LoginResult(result) => {
-                    self.app.logging_in = false;
-                    match result {
-                        Ok((auth_token, username)) => {
-                            // Save to localStorage
-                            save_auth(&auth_token, &username);
-                            
-                            self.app.auth_token = Some(auth_token.clone());
-                            self.app.username = username.clone();
-                            self.app.password.clear();
-                            self.app.login_error = None;
-                            self.app.loading_user_data = true;
-                            
-                            // Fetch user data
-                            self.fetch_user_data(username, auth_token);
-                        }
-                        Err(e) => {
-                            self.app.login_error = Some(e);
-                        }
-                    }
-                }
-                AppMessage::UserDataLoaded(result) => {
-                    self.app.loading_user_data = false;
-                    match result {
-                        Ok(user_data) => {
-                            self.app.user_data = Some(user_data);
-                            self.app.update_system_markers();
-                        }
-                        Err(e) => {
-                            tracing::warn!("Failed to load user data: {}", e);
-                        }
-                    }
-                }
-            }
+            self.handle_message(msg);
         }
         
-        // Handle login button click
-        if self.app.logging_in && self.app.auth_token.is_none() {
-            let username = self.app.username.clone();
-            let password = self.app.password.clone();
-            if !username.is_empty() && !password.is_empty() {
-                self.handle_login(username, password);
-                // Prevent re-triggering
-                self.app.logging_in = false;
-                self.app.logging_in = true; // Keep spinner showing
-            }
+        self.check_auth_expiry();
+        
+        // Handle star system load request (initial load or manual retry)
+        if self.app.star_systems_requested {
+            self.app.star_systems_requested = false;
+            self.app.offline_star_data = false;
+            self.app.loading = true;
+            self.app.loading_started_ms = now_ms();
+            self.app.star_download = None;
+            self.app.error = None;
+            self.fetch_star_systems(std::mem::take(&mut self.app.bypass_star_cache));
+        }
+        
+        if std::mem::take(&mut self.app.star_file_requested) {
+            self.import_star_systems();
+        }
+        if let Some((text, write)) = self.app.route_copy_pending.take() {
+            self.await_route_copy(text, write);
+        }
+        
+        // Feed newly requested systems into the detail queue and start as many as allowed
+        for system_id in self.app.pending_planet_requests.drain(..) {
+            self.detail_queue.push(system_id);
+        }
+        while let Some(system_id) = self.detail_queue.start_next() {
+            self.fetch_system_planets(system_id);
+        }
+        if self.detail_queue.is_busy() {
+            ctx.request_repaint();
+        }
+        
+        // Handle planet resource load request
+        if self.app.resources_requested {
+            self.app.resources_requested = false;
+            self.app.loading_resources = true;
+            self.app.resource_error = None;
+            self.fetch_planet_resources();
+        }
+        
+        // Handle exchange price load request
+        if self.app.exchange_prices_requested {
+            self.app.exchange_prices_requested = false;
+            self.app.loading_exchange_prices = true;
+            self.app.exchange_prices_error = None;
+            self.fetch_exchange_prices();
+        }
+        
+        // Dispatch a requested login exactly once; the result message returns the state to idle
+        if self.app.login_state == LoginState::Requested {
+            self.app.login_state = LoginState::InFlight;
+            self.handle_login(self.app.username.clone(), self.app.password.clone());
+        }
+        
+        // Wake up periodically so the token can be renewed even while the user is idle
+        if self.app.auth_expiry_ms.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_secs(30));
         }
         
         self.app.update(ctx, frame);
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{convex_hull, extract_system_from_planet, MapView, StarMapApp, MAX_ZOOM};
+    use crate::data::{StarSystem, SystemConnection};
+    use egui::pos2;
+    use petgraph::graph::NodeIndex;
+    use std::sync::Arc;
+
+    #[test]
+    fn planet_suffix_is_stripped() {
+        assert_eq!(extract_system_from_planet("UV-351a"), "UV-351");
+        assert_eq!(extract_system_from_planet("XK-745c"), "XK-745");
+    }
+
+    #[test]
+    fn multi_letter_suffix_is_stripped() {
+        assert_eq!(extract_system_from_planet("OT-580ba"), "OT-580");
+        assert_eq!(extract_system_from_planet("ZV-307cd"), "ZV-307");
+    }
+
+    #[test]
+    fn moon_digits_are_stripped() {
+        assert_eq!(extract_system_from_planet("OT-580b1"), "OT-580");
+        assert_eq!(extract_system_from_planet("KW-688c12"), "KW-688");
+    }
+
+    #[test]
+    fn system_ids_are_unchanged() {
+        assert_eq!(extract_system_from_planet("XK-745"), "XK-745");
+        assert_eq!(extract_system_from_planet("OT-580"), "OT-580");
+    }
+
+    #[test]
+    fn named_and_empty_ids_are_unchanged() {
+        // Named planets used to lose their last letter ("Montem" -> "Monte")
+        assert_eq!(extract_system_from_planet("Montem"), "Montem");
+        assert_eq!(extract_system_from_planet(""), "");
+    }
+
+    #[test]
+    fn hull_drops_interior_and_duplicate_points() {
+        let points = [pos2(2.0, 2.0), pos2(0.0, 0.0), pos2(1.0, 1.0), pos2(2.0, 0.0), pos2(0.0, 2.0), pos2(2.0, 2.0)];
+        assert_eq!(convex_hull(&points), [pos2(0.0, 0.0), pos2(2.0, 0.0), pos2(2.0, 2.0), pos2(0.0, 2.0)]);
+    }
+
+    #[test]
+    fn hull_of_collinear_points_is_its_endpoints() {
+        let points = [pos2(1.0, 1.0), pos2(0.0, 0.0), pos2(3.0, 3.0), pos2(2.0, 2.0)];
+        assert_eq!(convex_hull(&points), [pos2(0.0, 0.0), pos2(3.0, 3.0)]);
+    }
+
+    #[test]
+    fn hull_of_fewer_than_three_points_is_unchanged() {
+        assert!(convex_hull(&[]).is_empty());
+        assert_eq!(convex_hull(&[pos2(1.0, 1.0), pos2(1.0, 1.0)]), [pos2(1.0, 1.0)]);
+        assert_eq!(convex_hull(&[pos2(4.0, 0.0), pos2(1.0, 5.0)]), [pos2(1.0, 5.0), pos2(4.0, 0.0)]);
+    }
+
+    // World point (in projected coordinates) under a screen point relative to the map center
+    fn world_under(view: &MapView, pivot: egui::Vec2) -> egui::Vec2 {
+        (pivot - view.offset) / view.zoom
+    }
+
+    #[test]
+    fn zoom_keeps_pivot_in_place() {
+        let mut view = MapView { offset: egui::vec2(10.0, -5.0), ..MapView::default() };
+        let pivot = egui::vec2(100.0, 50.0);
+        let before = world_under(&view, pivot);
+        view.zoom_about(view.zoom * 2.0, pivot);
+        assert!((world_under(&view, pivot) - before).length() < 1e-3);
+    }
+
+    #[test]
+    fn saturated_zoom_does_not_move_view() {
+        let mut view = MapView { zoom: MAX_ZOOM, offset: egui::vec2(30.0, 20.0), ..MapView::default() };
+        view.zoom_about(MAX_ZOOM * 2.0, egui::vec2(-80.0, 40.0));
+        assert_eq!(view.zoom, MAX_ZOOM);
+        assert_eq!(view.offset, egui::vec2(30.0, 20.0));
+    }
+
+    #[test]
+    fn animated_zoom_past_limit_does_not_drift() {
+        let mut view = MapView::default();
+        let pivot = egui::vec2(120.0, -60.0);
+        let before = world_under(&view, pivot);
+        for _ in 0..20 {
+            view.zoom_by(4.0, pivot);
+            for _ in 0..10 {
+                view.update_zoom_animation(1.0 / 60.0);
+            }
+        }
+        while view.zoom_target.is_some() {
+            view.update_zoom_animation(1.0 / 60.0);
+        }
+        assert_eq!(view.zoom, MAX_ZOOM);
+        assert!((world_under(&view, pivot) - before).length() < 1e-2);
+    }
+
+    fn system(id: &str, connections: &[&str]) -> StarSystem {
+        StarSystem {
+            system_id: id.to_string(),
+            name: id.to_string(),
+            natural_id: id.to_string(),
+            star_type: "G".to_string(),
+            position_x: 0.0,
+            position_y: 0.0,
+            position_z: 0.0,
+            sector_id: String::new(),
+            sub_sector_id: String::new(),
+            connections: connections.iter()
+                .map(|&to| SystemConnection { system_connection_id: String::new(), connecting_id: to.to_string() })
+                .collect(),
+            user_name_submitted: String::new(),
+            timestamp: String::new(),
+        }
+    }
+
+    #[test]
+    fn loading_a_smaller_map_remaps_selection_and_route() {
+        let mut app = StarMapApp::default();
+        app.set_star_systems(vec![
+            system("A", &["B"]),
+            system("B", &["A", "C"]),
+            system("C", &["B", "D"]),
+            system("D", &["C", "E"]),
+            system("E", &["D"]),
+        ], 0);
+        let old = Arc::clone(app.star_map.as_ref().unwrap());
+        let old_idx = |id: &str| old.natural_id_to_node[id];
+        app.selected_star = Some(old_idx("C"));
+        app.selected_stars = [old_idx("B"), old_idx("E")].into();
+        app.waypoints = vec![old_idx("A"), old_idx("C")];
+        app.update_waypoint_route();
+        app.focus_center = Some(old_idx("E"));
+        app.measure_from = Some(old_idx("D"));
+
+        // Fewer systems, in a different order, so old indices are out of bounds or wrong
+        app.set_star_systems(vec![system("C", &["B"]), system("B", &["A", "C"]), system("A", &["B"])], 0);
+        let star_map = Arc::clone(app.star_map.as_ref().unwrap());
+        let id = |idx: NodeIndex| star_map.graph[idx].natural_id.as_str();
+
+        assert_eq!(app.selected_star.map(id), Some("C"));
+        assert_eq!(app.selected_stars.iter().map(|&idx| id(idx)).collect::<Vec<_>>(), ["B"]);
+        assert_eq!(app.waypoints.iter().map(|&idx| id(idx)).collect::<Vec<_>>(), ["A", "C"]);
+        assert_eq!(app.route.as_ref().unwrap().iter().map(|&idx| id(idx)).collect::<Vec<_>>(), ["A", "B", "C"]);
+        assert_eq!(app.focus_center, None);
+        assert_eq!(app.measure_from, None);
+    }
+}